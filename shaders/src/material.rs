@@ -24,6 +24,11 @@ pub struct LowPolyMaterial {
     #[render_resources(ignore)]
     #[shader_def]
     pub unlit: bool,
+    /// Light using the mesh's `Vertex_Normal`s rather than the facets of
+    /// its triangles.
+    #[render_resources(ignore)]
+    #[shader_def]
+    pub vertex_normals: bool,
     // Per face materials.
     #[render_resources(buffer)]
     pub per_face_data: Vec<PerFaceData>,
@@ -96,6 +101,7 @@ impl Default for LowPolyMaterial {
             occlusion_texture: None,
             emissive_texture: None,
             unlit: false,
+            vertex_normals: false,
             per_face_data: vec![],
         }
     }
@@ -46,7 +46,6 @@ struct PointLight {
 };
 
 layout(location = 0) in vec3 v_WorldPosition;
-//layout(location = 1) flat in vec3 v_WorldNormal;
 layout(location = 1) in vec2 v_Uv;
 
 layout(location = 2) flat in uint v_Per_Face_Index;
@@ -55,6 +54,10 @@ layout(location = 3) in float v_DistanceEdge;
 
 layout(location = 4) in vec3 v_VertexPosition;
 
+#ifdef LOWPOLYMATERIAL_VERTEX_NORMALS
+layout(location = 5) in vec3 v_WorldNormal;
+#endif
+
 layout(location = 0) out vec4 o_Target;
 
 layout(set = 0, binding = 0) uniform CameraViewProj {
@@ -298,9 +301,13 @@ void main() {
     reflectance *= edge_value;
     uint flags =                    PerFaceData[v_Per_Face_Index].flags;
 
-    vec3 v_WorldNormal = normalize(cross(dFdy(v_WorldPosition), dFdx(v_WorldPosition)));
-//    vec4 base_color = vec4(calc_colour(v_WorldNormal), 1.0);
-    vec4 v_WorldTangent = vec4(cross(cross(v_WorldNormal, vec3(0.0, 1.0, 0.0)), v_WorldNormal), 1.0);
+    #ifdef LOWPOLYMATERIAL_VERTEX_NORMALS
+    vec3 world_normal = normalize(v_WorldNormal);
+    #else
+    vec3 world_normal = normalize(cross(dFdy(v_WorldPosition), dFdx(v_WorldPosition)));
+    #endif
+//    vec4 base_color = vec4(calc_colour(world_normal), 1.0);
+    vec4 v_WorldTangent = vec4(cross(cross(world_normal, vec3(0.0, 1.0, 0.0)), world_normal), 1.0);
 
     vec4 output_color = base_color;
     #ifdef LOWPOLYMATERIAL_BASE_COLOR_TEXTURE
@@ -322,7 +329,7 @@ void main() {
 
     float roughness = perceptualRoughnessToRoughness(perceptual_roughness);
 
-    vec3 N = v_WorldNormal;
+    vec3 N = world_normal;
 
     #    ifdef LOWPOLYMATERIAL_DOUBLE_SIDED
     N = gl_FrontFacing ? N : -N;
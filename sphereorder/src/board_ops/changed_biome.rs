@@ -0,0 +1,19 @@
+use crate::{Board, BiomeRegions, TileData, TileIndex};
+use bevy::ecs::query::Changed;
+use bevy::ecs::system::Query;
+use bevy::transform::components::Parent;
+
+pub(crate) fn update_biome_regions_system(
+    changed: Query<(&Parent, &TileIndex, &TileData), Changed<TileData>>,
+    mut shells: Query<(&Board, &mut BiomeRegions)>,
+) {
+    for (parent, &TileIndex(tile), data) in changed.iter() {
+        let (board, mut regions) = if let Ok(x) = shells.get_mut(parent.0) {
+            x
+        } else {
+            continue;
+        };
+
+        regions.set_biome(board.topology(), tile, data.biome);
+    }
+}
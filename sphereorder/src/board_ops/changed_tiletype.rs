@@ -1,15 +1,14 @@
+use crate::board_ops::ShellChunk;
 use crate::{FaceMaterialIdx, OldFaceMaterialIdx, TileDataIdx};
 use bevy::asset::{Assets, Handle};
 use bevy::ecs::query::Changed;
 use bevy::ecs::system::{Query, ResMut};
 use bevy::render::mesh::{Mesh, VertexAttributeValues};
-use bevy::transform::components::Parent;
-use bevy::transform::prelude::Children;
 use bevy::utils::HashSet;
 
 pub(crate) fn update_material_idx_system(
-    children_query: Query<(&Handle<Mesh>, &Children)>,
-    first_query: Query<&Parent, Changed<FaceMaterialIdx>>,
+    chunk_query: Query<(&Handle<Mesh>, &ShellChunk)>,
+    first_query: Query<&TileDataIdx, Changed<FaceMaterialIdx>>,
     mut query: Query<
         (&TileDataIdx, &FaceMaterialIdx, &mut OldFaceMaterialIdx),
         Changed<FaceMaterialIdx>,
@@ -18,11 +17,11 @@ pub(crate) fn update_material_idx_system(
 ) {
     first_query
         .iter()
-        .map(|x| x.0)
+        .map(|x| x.chunk)
         .collect::<HashSet<_>>()
         .into_iter()
-        .for_each(move |planet_with_changed_tile| {
-            let (mesh_handle, children) = children_query.get(planet_with_changed_tile).unwrap();
+        .for_each(move |chunk_with_changed_tile| {
+            let (mesh_handle, chunk) = chunk_query.get(chunk_with_changed_tile).unwrap();
 
             meshes
                 .get_mut(mesh_handle)
@@ -30,17 +29,17 @@ pub(crate) fn update_material_idx_system(
                 .attribute_mut(shaders::ATTRIBUTE_PER_FACE_INDEX)
                 .map(|attribs| match attribs {
                     VertexAttributeValues::Sint32(v) => {
-                        children.iter().for_each(|child| {
-                            let child = query.get_mut(*child);
-                            let (member_data, new_face, mut old_face) = if let Ok(x) = child {
+                        chunk.tiles.iter().for_each(|tile| {
+                            let tile = query.get_mut(*tile);
+                            let (member_data, new_face, mut old_face) = if let Ok(x) = tile {
                                 x
                             } else {
                                 return;
                             };
 
-                            let idx = member_data.0;
-                            old_face.0 = v[idx];
-                            v[idx] = new_face.0;
+                            let vertices = &member_data.vertices;
+                            old_face.0 = v[vertices[0] as usize];
+                            vertices.iter().for_each(|&x| v[x as usize] = new_face.0);
                         });
                     }
                     _ => panic!(),
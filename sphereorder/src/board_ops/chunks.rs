@@ -0,0 +1,94 @@
+use crate::board_ops::{Layers, ShellLod};
+use crate::camera::SphereCamera;
+use crate::ShellDimensions;
+use bevy::ecs::entity::Entity;
+use bevy::ecs::prelude::With;
+use bevy::ecs::system::Query;
+use bevy::math::Vec3;
+use bevy::render::draw::Visible;
+use bevy::transform::components::{GlobalTransform, Parent};
+use bevy::utils::HashMap;
+
+/// One piece of a shell's mesh, as a child of the shell.
+///
+/// Changing a tile only updates the mesh of the chunk it's in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShellChunk {
+    /// The tiles in this chunk.
+    pub tiles: Vec<Entity>,
+    // A cone from the centre of the shell containing the whole chunk,
+    // in the shell's local space.
+    pub(crate) axis: Vec3,
+    pub(crate) spread: f32,
+    // The furthest the chunk gets from the centre, as a fraction of
+    // the shell's radius.
+    pub(crate) height: f32,
+}
+
+/// Hides the chunks which are behind their planet, or whose shell is
+/// showing its [`ShellLod`] instead.
+///
+/// The innermost shell of a planet's [`Layers`] is assumed to be solid,
+/// since the outer shells let you see through to their far side.
+/// Shells which aren't part of a layered planet only hide themselves.
+pub(crate) fn cull_chunks_system(
+    cameras: Query<&GlobalTransform, With<SphereCamera>>,
+    planets: Query<&Layers>,
+    shells: Query<(&GlobalTransform, &ShellDimensions)>,
+    lods: Query<&ShellLod>,
+    mut chunks: Query<(&Parent, &ShellChunk, &mut Visible)>,
+) {
+    let camera = if let Ok(x) = cameras.single() {
+        x.translation
+    } else {
+        return;
+    };
+
+    let occluders = planets
+        .iter()
+        .filter_map(|layers| {
+            let innermost = *layers.first()?;
+            let (transform, dimensions) = shells.get(innermost).ok()?;
+            let occluder = (transform.translation, dimensions.inner_radius);
+            Some(layers.iter().map(move |&shell| (shell, occluder)))
+        })
+        .flatten()
+        .collect::<HashMap<_, _>>();
+
+    for (parent, chunk, mut visible) in chunks.iter_mut() {
+        let (transform, dimensions) = if let Ok(x) = shells.get(parent.0) {
+            x
+        } else {
+            continue;
+        };
+
+        let (centre, radius) = occluders
+            .get(&parent.0)
+            .copied()
+            .unwrap_or((transform.translation, dimensions.inner_radius));
+
+        let to_camera = camera - centre;
+        let distance = to_camera.length();
+
+        let lod_active = lods.get(parent.0).map(|lod| lod.active).unwrap_or(false);
+
+        let is_visible = if lod_active {
+            false
+        } else if distance <= radius {
+            true
+        } else {
+            let axis = transform.rotation * chunk.axis;
+            let furthest = (chunk.height * dimensions.radius).max(radius);
+            // Anything further around than this from the camera is
+            // behind the occluder.
+            let horizon = (radius / distance).acos() + (radius / furthest).acos();
+            let angle = axis.dot(to_camera / distance).max(-1.0).min(1.0).acos();
+
+            angle - chunk.spread < horizon
+        };
+
+        if visible.is_visible != is_visible {
+            visible.is_visible = is_visible;
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{BoardBuilder, PlanetDesc, ShellParts, TopologyCache};
+use bevy::asset::Assets;
+use bevy::ecs::entity::Entity;
+use bevy::ecs::query::Added;
+use bevy::ecs::system::{Commands, Query, Res, ResMut};
+use bevy::render::mesh::Mesh;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use futures_lite::future;
+use shaders::LowPolyMaterial;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A shell being built on the [`AsyncComputeTaskPool`]. It's removed
+/// once the shell has been spawned.
+pub struct GeneratingShell {
+    task: Task<ShellParts>,
+    steps: Arc<AtomicUsize>,
+}
+
+/// How far along the shells being generated are, for showing on a
+/// loading screen.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct GenerationProgress {
+    /// Every shell which has started generating, including finished
+    /// ones.
+    pub started: usize,
+    /// Shells which have been spawned.
+    pub finished: usize,
+    /// From 0 to 1, how much of the work of every started shell is
+    /// done.
+    pub fraction: f32,
+}
+
+impl GenerationProgress {
+    pub fn is_finished(&self) -> bool {
+        self.finished == self.started
+    }
+}
+
+pub(crate) fn start_generating_system(
+    query: Query<(Entity, &PlanetDesc), Added<PlanetDesc>>,
+    mut commands: Commands,
+    pool: Res<AsyncComputeTaskPool>,
+    topologies: Res<TopologyCache>,
+    mut progress: ResMut<GenerationProgress>,
+) {
+    for (new_planet, planet) in query.iter() {
+        let builder = BoardBuilder::from(*planet);
+        let steps = Arc::new(AtomicUsize::new(0));
+        let task_steps = steps.clone();
+        let topologies = topologies.clone();
+
+        let task = pool.spawn(async move { builder.build(&topologies, &task_steps) });
+
+        commands
+            .entity(new_planet)
+            .insert(GeneratingShell { task, steps });
+        progress.started += 1;
+    }
+}
+
+pub(crate) fn finish_generating_system(
+    mut query: Query<(Entity, &PlanetDesc, &mut GeneratingShell)>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut planet_materials: ResMut<Assets<LowPolyMaterial>>,
+    mut progress: ResMut<GenerationProgress>,
+) {
+    let mut pending_steps = 0;
+    for (planet, desc, mut generating) in query.iter_mut() {
+        match future::block_on(future::poll_once(&mut generating.task)) {
+            Some(parts) => {
+                BoardBuilder::from(*desc).spawn(
+                    parts,
+                    &mut commands,
+                    planet,
+                    &mut *meshes,
+                    &mut *planet_materials,
+                );
+                commands.entity(planet).remove::<GeneratingShell>();
+                progress.finished += 1;
+            }
+            None => pending_steps += generating.steps.load(Ordering::Relaxed),
+        }
+    }
+
+    let total = progress.started * BoardBuilder::BUILD_STEPS;
+    let done = progress.finished * BoardBuilder::BUILD_STEPS + pending_steps;
+    progress.fraction = if total == 0 {
+        1.0
+    } else {
+        done as f32 / total as f32
+    };
+}
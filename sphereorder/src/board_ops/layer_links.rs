@@ -0,0 +1,93 @@
+use crate::board_ops::Layers;
+use crate::{Board, PlanetDesc, TileOverlaps, TopologyCache};
+use bevy::ecs::entity::Entity;
+use bevy::ecs::query::{With, Without};
+use bevy::ecs::system::{Commands, Query, Res};
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use futures_lite::future;
+use std::sync::Arc;
+
+/// How the tiles of each pair of adjacent layers line up, stored on the
+/// entity with the [`Layers`].
+///
+/// `self.0[k]` relates `layers[k]` (the lower shell) to `layers[k + 1]`
+/// (the upper shell). Tiles are given by index into each shell's
+/// [`Board::tiles`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerLinks(pub Vec<Arc<TileOverlaps>>);
+
+impl LayerLinks {
+    /// The tiles of layer `layer + 1` over `tile` of layer `layer`,
+    /// with the fraction of `tile` each one covers. Empty for the top
+    /// layer.
+    pub fn above(&self, layer: usize, tile: usize) -> &[(u32, f32)] {
+        match self.0.get(layer) {
+            Some(overlaps) => overlaps.above(tile),
+            None => &[],
+        }
+    }
+
+    /// The tiles of layer `layer - 1` under `tile` of layer `layer`,
+    /// with the fraction of `tile` each one covers. Empty for the
+    /// bottom layer.
+    pub fn below(&self, layer: usize, tile: usize) -> &[(u32, f32)] {
+        match layer.checked_sub(1).and_then(|lower| self.0.get(lower)) {
+            Some(overlaps) => overlaps.below(tile),
+            None => &[],
+        }
+    }
+}
+
+/// [`LayerLinks`] being worked out on the [`AsyncComputeTaskPool`].
+/// It's removed once they've been added.
+pub struct LinkingLayers(Task<Vec<Arc<TileOverlaps>>>);
+
+pub(crate) fn link_layers_system(
+    mut commands: Commands,
+    planets: Query<(Entity, &Layers), (Without<LayerLinks>, Without<LinkingLayers>)>,
+    descs: Query<&PlanetDesc, With<Board>>,
+    pool: Res<AsyncComputeTaskPool>,
+    topologies: Res<TopologyCache>,
+) {
+    for (planet, layers) in planets.iter() {
+        // Wait until every shell has been built.
+        let shells = if let Some(x) = layers
+            .iter()
+            .map(|&shell| {
+                descs
+                    .get(shell)
+                    .ok()
+                    .map(|desc| (desc.subvidisions, desc.shape))
+            })
+            .collect::<Option<Vec<_>>>()
+        {
+            x
+        } else {
+            continue;
+        };
+
+        let topologies = topologies.clone();
+        let task = pool.spawn(async move {
+            shells
+                .windows(2)
+                .map(|pair| topologies.overlaps(pair[0], pair[1]))
+                .collect()
+        });
+
+        commands.entity(planet).insert(LinkingLayers(task));
+    }
+}
+
+pub(crate) fn finish_linking_layers_system(
+    mut commands: Commands,
+    mut planets: Query<(Entity, &mut LinkingLayers)>,
+) {
+    for (planet, mut linking) in planets.iter_mut() {
+        if let Some(links) = future::block_on(future::poll_once(&mut linking.0)) {
+            commands
+                .entity(planet)
+                .remove::<LinkingLayers>()
+                .insert(LayerLinks(links));
+        }
+    }
+}
@@ -1,59 +1,151 @@
 use bevy::ecs::entity::Entity;
 use bevy::ecs::system::{ResMut, Commands, Query};
-use crate::{RelationPair, NeighbourOf, PackedRelations};
+use bevy::ecs::world::World;
+use crate::{Board, NeighbourOf, PackedRelations};
+use std::time::{Duration, Instant};
 
+/// The shell being loaded, and how many of its
+/// [`PackedRelations`] entries have been inserted so far.
 pub struct LoadState(
     pub Option<Entity>,
-    pub Option<std::collections::hash_map::IntoIter<RelationPair, NeighbourOf>>
+    pub Option<usize>,
 );
 
-pub fn loader<const PER_ITER: usize>(
-    mut state: ResMut<LoadState>,
-    mut commands: Commands,
-    query: Query<&PackedRelations>,
-) {
-    let planet_shell = if let Some(entity) = state.0 {
-        entity
-    } else {
-        return;
-    };
+/// How much loading and unloading of relations may be done each
+/// frame, shared between the two.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RelationBudget {
+    /// Stop once this much time has been spent.
+    Time(Duration),
+    /// Insert or remove at most this many relations.
+    Relations(usize),
+}
 
-    let mut iter = state
-        .1
-        .get_or_insert_with(|| {
-            query
-                .get(planet_shell)
-                .unwrap()
-                .relations
-                .clone()
-                .into_iter()
-        });
+impl Default for RelationBudget {
+    fn default() -> Self {
+        RelationBudget::Time(Duration::from_millis(2))
+    }
+}
 
-    // &mut I is also an iterator, and does not consume the I.
-    let executions = (&mut iter)
-        .take(PER_ITER)
-        .map(|(RelationPair(a, b), data)| {
-            commands
-                .entity(a)
-                .insert_relation(data.clone(), b);
+/// How far along loading the current shell is.
+///
+/// This is counted in relations: every pair of neighbouring tiles is
+/// two, one from each side.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct LoadProgress {
+    pub shell: Option<Entity>,
+    pub loaded: usize,
+    pub total: usize,
+}
 
-            commands
-                .entity(b)
-                .insert_relation(data, a);
-        })
-        .count();
-
-    // If we didn't get all of the iterations we should've, we're done.
-    if executions != PER_ITER {
-        state.0 = None;
-        state.1 = None;
+impl LoadProgress {
+    pub fn is_finished(&self) -> bool {
+        self.loaded == self.total
+    }
+}
+
+// How many relations are done between checks of the clock.
+const TIME_BATCH: usize = 64;
+
+/// What's left of a frame's [`RelationBudget`].
+pub(crate) struct BudgetTracker {
+    budget: RelationBudget,
+    start: Instant,
+    spent: usize,
+}
+
+impl BudgetTracker {
+    pub fn new(budget: RelationBudget) -> Self {
+        Self {
+            budget,
+            start: Instant::now(),
+            spent: 0,
+        }
+    }
+
+    /// How many relations to do before asking again.
+    pub fn allowance(&self) -> usize {
+        match self.budget {
+            RelationBudget::Time(time) if self.start.elapsed() < time => TIME_BATCH,
+            RelationBudget::Time(_) => 0,
+            RelationBudget::Relations(count) => count.saturating_sub(self.spent),
+        }
+    }
+
+    pub fn spend(&mut self, relations: usize) {
+        self.spent += relations;
+    }
+}
+
+/// Up to `count` of `shell`'s relations, starting from the `start`th.
+pub(crate) fn relation_batch(
+    world: &World,
+    shell: Entity,
+    start: usize,
+    count: usize,
+) -> Vec<(Entity, Entity)> {
+    match (world.get::<PackedRelations>(shell), world.get::<Board>(shell)) {
+        (Some(relations), Some(board)) => relations
+            .entries_from(start)
+            .take(count)
+            .map(|(a, b)| (board.tiles()[a as usize], board.tiles()[b as usize]))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Inserts as many relations as the budget left over by
+/// [`unload_some`](super::unload::unload_some) allows.
+pub(crate) fn load_some(world: &mut World, budget: &mut BudgetTracker) {
+    loop {
+        let state = world.get_resource::<LoadState>().unwrap();
+        let (planet_shell, start) = if let Some(entity) = state.0 {
+            (entity, state.1.unwrap_or(0))
+        } else {
+            return;
+        };
+
+        let allowance = budget.allowance();
+        if allowance == 0 {
+            return;
+        }
+
+        let batch = relation_batch(world, planet_shell, start, allowance);
+        for &(a, b) in &batch {
+            if let Some(mut entity) = world.get_entity_mut(a) {
+                entity.insert_relation(NeighbourOf, b);
+            }
+        }
+        budget.spend(batch.len());
+
+        let total = world
+            .get::<PackedRelations>(planet_shell)
+            .map(PackedRelations::entry_count)
+            .unwrap_or(0);
+        let loaded = start + batch.len();
+        *world.get_resource_mut::<LoadProgress>().unwrap() = LoadProgress {
+            shell: Some(planet_shell),
+            loaded: loaded.min(total),
+            total,
+        };
+
+        let mut state = world.get_resource_mut::<LoadState>().unwrap();
+        // If we didn't get all of the iterations we should've, we're done.
+        if batch.len() < allowance {
+            state.0 = None;
+            state.1 = None;
+            return;
+        } else {
+            state.1 = Some(loaded);
+        }
     }
 }
 
 pub fn load_all(
     mut state: ResMut<LoadState>,
+    mut progress: ResMut<LoadProgress>,
     mut commands: Commands,
-    query: Query<&PackedRelations>,
+    query: Query<(&PackedRelations, &Board)>,
 ) {
     let planet_shell = if let Some(entity) = state.0 {
         entity
@@ -61,27 +153,20 @@ pub fn load_all(
         return;
     };
 
-    let mut iter = state
-        .1
-        .take()
-        .unwrap_or_else(|| {
-            query
-                .get(planet_shell)
-                .unwrap()
-                .relations
-                .clone()
-                .into_iter()
-        });
-
-    // &mut I is also an iterator, and does not consume the I.
-    iter
-        .for_each(|(RelationPair(a, b), data)| {
-            commands
-                .entity(a)
-                .insert_relation(data.clone(), b);
+    let (relations, board) = query.get(planet_shell).unwrap();
+    let start = state.1.take().unwrap_or(0);
 
+    relations
+        .entries_from(start)
+        .for_each(|(a, b)| {
             commands
-                .entity(b)
-                .insert_relation(data, a);
+                .entity(board.tiles()[a as usize])
+                .insert_relation(NeighbourOf, board.tiles()[b as usize]);
         });
+
+    *progress = LoadProgress {
+        shell: Some(planet_shell),
+        loaded: relations.entry_count(),
+        total: relations.entry_count(),
+    };
 }
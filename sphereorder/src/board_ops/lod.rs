@@ -0,0 +1,122 @@
+use crate::camera::SphereCamera;
+use crate::mesh::MaterialVertices;
+use crate::{Board, FaceMaterialIdx, ShellDimensions, TileIndex, TileOverlaps};
+use bevy::asset::{Assets, Handle};
+use bevy::ecs::entity::Entity;
+use bevy::ecs::prelude::With;
+use bevy::ecs::query::Changed;
+use bevy::ecs::system::{Query, ResMut};
+use bevy::render::draw::Visible;
+use bevy::render::mesh::{Mesh, VertexAttributeValues};
+use bevy::transform::components::{GlobalTransform, Parent};
+use bevy::utils::{HashMap, HashSet};
+use smallvec::SmallVec;
+use std::sync::Arc;
+
+/// A lower detail stand-in for a shell, shown instead of the shell's
+/// chunks when the camera is far away.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShellLod {
+    /// The entity with the stand-in's mesh, a child of the shell.
+    pub proxy: Entity,
+    /// How far the camera must be from the centre of the shell, in
+    /// multiples of its radius, before the proxy is shown.
+    pub distance: f32,
+    /// Whether the proxy is currently shown.
+    pub active: bool,
+    // Lower is the proxy, upper is the shell.
+    pub(crate) overlaps: Arc<TileOverlaps>,
+    pub(crate) proxy_vertices: Vec<MaterialVertices>,
+}
+
+// The material covering the most area.
+fn dominant(overlapping: &[(u32, f32)], mut material: impl FnMut(usize) -> i32) -> i32 {
+    let mut totals = SmallVec::<[(i32, f32); 8]>::new();
+    for &(tile, fraction) in overlapping {
+        let material = material(tile as usize);
+        match totals.iter_mut().find(|(x, _)| *x == material) {
+            Some((_, total)) => *total += fraction,
+            None => totals.push((material, fraction)),
+        }
+    }
+
+    totals
+        .into_iter()
+        .fold(None, |acc: Option<(i32, f32)>, (material, total)| match acc {
+            Some((_, best)) if best >= total => acc,
+            _ => Some((material, total)),
+        })
+        .map(|(material, _)| material)
+        .unwrap_or(0)
+}
+
+pub(crate) fn switch_lod_system(
+    cameras: Query<&GlobalTransform, With<SphereCamera>>,
+    mut shells: Query<(&GlobalTransform, &ShellDimensions, &mut ShellLod)>,
+    mut proxies: Query<&mut Visible>,
+) {
+    let camera = if let Ok(x) = cameras.single() {
+        x.translation
+    } else {
+        return;
+    };
+
+    for (transform, dimensions, mut lod) in shells.iter_mut() {
+        let distance = (camera - transform.translation).length();
+        let active = distance > lod.distance * dimensions.radius;
+
+        if lod.active != active {
+            lod.active = active;
+            if let Ok(mut visible) = proxies.get_mut(lod.proxy) {
+                visible.is_visible = active;
+            }
+        }
+    }
+}
+
+/// Recolours the proxy tiles over any tiles whose material changed.
+pub(crate) fn update_lod_colours_system(
+    changed: Query<(&Parent, &TileIndex), Changed<FaceMaterialIdx>>,
+    faces: Query<&FaceMaterialIdx>,
+    shells: Query<(&Board, &ShellLod)>,
+    proxies: Query<&Handle<Mesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let mut dirty = HashMap::<Entity, HashSet<u32>>::default();
+    for (parent, &TileIndex(tile)) in changed.iter() {
+        if let Ok((_, lod)) = shells.get(parent.0) {
+            dirty
+                .entry(parent.0)
+                .or_insert_with(HashSet::default)
+                .extend(lod.overlaps.below(tile).iter().map(|&(x, _)| x));
+        }
+    }
+
+    for (shell, proxy_tiles) in dirty {
+        let (board, lod) = shells.get(shell).unwrap();
+        let mesh_handle = proxies.get(lod.proxy).unwrap();
+
+        meshes
+            .get_mut(mesh_handle)
+            .unwrap()
+            .attribute_mut(shaders::ATTRIBUTE_PER_FACE_INDEX)
+            .map(|attribs| match attribs {
+                VertexAttributeValues::Sint32(v) => {
+                    proxy_tiles.into_iter().for_each(|proxy_tile| {
+                        let material = dominant(lod.overlaps.above(proxy_tile as usize), |tile| {
+                            faces
+                                .get(board.tiles()[tile])
+                                .map(|x| x.0)
+                                .unwrap_or(0)
+                        });
+
+                        lod.proxy_vertices[proxy_tile as usize]
+                            .iter()
+                            .for_each(|&x| v[x as usize] = material);
+                    });
+                }
+                _ => panic!(),
+            })
+            .unwrap();
+    }
+}
@@ -1,22 +1,28 @@
 use bevy::app::{AppBuilder, Plugin};
-use bevy::ecs::system::{Commands, Query, ResMut};
-use crate::PlanetDesc;
-use bevy::asset::Assets;
+use bevy::ecs::system::{IntoExclusiveSystem, Local, ResMut};
+use bevy::ecs::world::World;
 use bevy::ecs::entity::Entity;
-use bevy::ecs::query::Added;
-use bevy::render::mesh::Mesh;
 pub use select_tile::PlanetTileRaycastSet;
-use shaders::LowPolyMaterial;
-use bevy::ecs::schedule::{SystemSet, State};
+pub use layer_links::{LayerLinks, LinkingLayers};
+pub use chunks::ShellChunk;
+pub use lod::ShellLod;
+pub use generate::{GeneratingShell, GenerationProgress};
+use bevy::ecs::schedule::{ParallelSystemDescriptorCoercion, SystemSet, State};
 use bevy::ecs::event::EventReader;
 use crate::camera::LayerChangeEvent;
-use crate::board_ops::load::LoadState;
+use crate::board_ops::load::{BudgetTracker, LoadState};
+pub use load::{LoadProgress, RelationBudget};
 use crate::board_ops::unload::UnloadState;
 use bevy::transform::components::Parent;
 use smallvec::SmallVec;
 use std::ops::Deref;
 
+mod changed_biome;
+mod chunks;
 mod changed_tiletype;
+mod generate;
+mod layer_links;
+mod lod;
 mod select_tile;
 mod unload;
 mod load;
@@ -64,40 +70,44 @@ pub enum LayerLoadState {
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_system(changed_tiletype::update_material_idx_system)
-            .add_system(Self::add_new_planets)
+            .add_system(changed_biome::update_biome_regions_system)
+            .add_system(generate::start_generating_system)
+            .add_system(generate::finish_generating_system)
+            .init_resource::<GenerationProgress>()
+            .init_resource::<crate::TopologyCache>()
+            .add_system(layer_links::link_layers_system)
+            .add_system(layer_links::finish_linking_layers_system)
+            .add_system(chunks::cull_chunks_system)
+            .add_system(lod::switch_lod_system)
+            .add_system(lod::update_lod_colours_system)
             .add_state(LayerLoadState::Finished)
             .insert_resource(LoadState(None, None))
-            .insert_resource(UnloadState(Default::default()))
+            .insert_resource(UnloadState(Default::default(), None))
+            .init_resource::<RelationBudget>()
+            .init_resource::<LoadProgress>()
             .add_system_set(
                 SystemSet::on_update(LayerLoadState::LoadUnload)
-                    .with_system(load::loader::<5>)
-                    // .with_system(unload::unloader::<5>)
+                    .with_system(Self::load_unload.exclusive_system())
                     .with_system(Self::layer_state_resetter)
             )
             .add_system_set(
                 SystemSet::on_exit(LayerLoadState::LoadUnload)
-                    .with_system(load::load_all)
-                    // .with_system(unload::unload_all)
+                    .with_system(unload::unload_all.label("unload_all"))
+                    .with_system(load::load_all.after("unload_all"))
             )
             .add_system(Self::layer_event_watcher);
     }
 }
 
 impl BoardPlugin {
-    fn add_new_planets(
-        query: Query<(Entity, &PlanetDesc), Added<PlanetDesc>>,
-        mut commands: Commands,
-        mut meshes: ResMut<Assets<Mesh>>,
-        mut planet_materials: ResMut<Assets<LowPolyMaterial>>,
-    ) {
-        for (new_planet, planet) in query.iter() {
-            crate::BoardBuilder::from(*planet).create_on(
-                &mut commands,
-                new_planet,
-                &mut *meshes,
-                &mut *planet_materials,
-            );
-        }
+    fn load_unload(world: &mut World) {
+        let budget = *world.get_resource::<RelationBudget>().unwrap();
+        let mut budget = BudgetTracker::new(budget);
+
+        // Unloading first means a shell's relations are never removed
+        // after being loaded in the same frame.
+        unload::unload_some(world, &mut budget);
+        load::load_some(world, &mut budget);
     }
 
     fn layer_event_watcher(
@@ -105,14 +115,27 @@ impl BoardPlugin {
         mut state: ResMut<State<LayerLoadState>>,
         mut load: ResMut<LoadState>,
         mut unload: ResMut<UnloadState>,
+        // The shell which is loaded, or being loaded.
+        mut current: Local<Option<Entity>>,
     ) {
         for event in events.iter() {
-            load
-                .0
+            if *current == Some(event.new_shell) {
+                continue;
+            }
+
+            // Coming back to a shell before it's finished unloading
+            // cancels the unload, it'll be loaded from the start again.
+            if unload.0.first() == Some(&event.new_shell) {
+                unload.1 = None;
+            }
+            unload.0.retain(|x| *x != event.new_shell);
+
+            current
                 .replace(event.new_shell)
                 .into_iter()
                 .for_each(|to_unload| unload.0.push(to_unload));
 
+            load.0 = Some(event.new_shell);
             load.1 = None;
 
             if let LayerLoadState::Finished = state.current() {
@@ -1,4 +1,72 @@
 use smallvec::SmallVec;
 use bevy::ecs::entity::Entity;
+use bevy::ecs::system::{ResMut, Commands, Query};
+use bevy::ecs::world::World;
+use crate::board_ops::load::{relation_batch, BudgetTracker};
+use crate::{Board, NeighbourOf, PackedRelations};
 
-pub struct UnloadState(pub SmallVec<[Entity; 2]>);
+/// The shells waiting to have their relations removed, and how many of
+/// the first one's [`PackedRelations`] entries have been removed so
+/// far.
+///
+/// A shell is never in here while it's being loaded, see
+/// `BoardPlugin::layer_event_watcher`.
+pub struct UnloadState(pub SmallVec<[Entity; 2]>, pub Option<usize>);
+
+/// Removes as many relations as the budget allows, leaving the rest
+/// of it for [`load_some`](super::load::load_some).
+pub(crate) fn unload_some(world: &mut World, budget: &mut BudgetTracker) {
+    loop {
+        let state = world.get_resource::<UnloadState>().unwrap();
+        let (planet_shell, start) = if let Some(&entity) = state.0.first() {
+            (entity, state.1.unwrap_or(0))
+        } else {
+            return;
+        };
+
+        let allowance = budget.allowance();
+        if allowance == 0 {
+            return;
+        }
+
+        // A shell which is gone has taken its relations with it.
+        let batch = relation_batch(world, planet_shell, start, allowance);
+        for &(a, b) in &batch {
+            if let Some(mut entity) = world.get_entity_mut(a) {
+                entity.remove_relation::<NeighbourOf>(b);
+            }
+        }
+        budget.spend(batch.len());
+
+        let mut state = world.get_resource_mut::<UnloadState>().unwrap();
+        // If we didn't use up the allowance, this shell is done.
+        if batch.len() < allowance {
+            state.0.remove(0);
+            state.1 = None;
+        } else {
+            state.1 = Some(start + batch.len());
+        }
+    }
+}
+
+pub fn unload_all(
+    mut state: ResMut<UnloadState>,
+    mut commands: Commands,
+    query: Query<(&PackedRelations, &Board)>,
+) {
+    let mut start = state.1.take().unwrap_or(0);
+
+    for planet_shell in state.0.drain(..) {
+        if let Ok((relations, board)) = query.get(planet_shell) {
+            relations
+                .entries_from(start)
+                .for_each(|(a, b)| {
+                    commands
+                        .entity(board.tiles()[a as usize])
+                        .remove_relation::<NeighbourOf>(board.tiles()[b as usize]);
+                });
+        }
+
+        start = 0;
+    }
+}
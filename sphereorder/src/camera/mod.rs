@@ -11,6 +11,7 @@ use bevy_utils::Instant;
 use bevy::prelude::KeyCode;
 use bevy::app::EventWriter;
 use crate::board_ops::Layers;
+use crate::ShellDimensions;
 // use bevy_inspector_egui::Inspectable;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -100,7 +101,7 @@ pub fn move_cameras(
     mut mouse_wheel_events: EventReader<MouseWheel>,
     speed_config: Res<CameraSpeedConfig>,
     keyboard: Res<Input<KeyCode>>,
-    transforms: Query<&Transform>,
+    shells: Query<&ShellDimensions>,
     mut events: EventWriter<LayerChangeEvent>,
 ) {
     // Mouse motion total
@@ -200,10 +201,10 @@ pub fn move_cameras(
 
     let new_max = layers
         .get(new_target + 1)
-        .map(|&x| transforms.get(x).unwrap().scale.x - 0.1)
+        .map(|&x| shells.get(x).unwrap().radius - 0.1)
         .unwrap_or(f32::INFINITY);
 
-    let new_scl = transforms.get(layers[new_target]).unwrap().scale.x;
+    let new_scl = shells.get(layers[new_target]).unwrap().radius;
 
     let new_dst = new_max - new_scl;
 
@@ -293,13 +294,13 @@ pub fn update_camera_transform(
 pub fn added_camera(
     mut cameras: Query<(Entity, &mut SphereCamera), Added<SphereCamera>>,
     layers: Query<&Layers>,
-    transforms: Query<&Transform>,
+    shells: Query<&ShellDimensions>,
     mut commands: Commands,
 ) {
     cameras
         .iter_mut()
         .for_each(move |(entity, mut x)| {
-            x.scale = transforms.get(layers.get(x.layered_planet).unwrap()[x.target_layer]).unwrap().scale.x;
+            x.scale = shells.get(layers.get(x.layered_planet).unwrap()[x.target_layer]).unwrap().radius;
             commands
                 .entity(entity)
                 .insert(None::<TargetSphereCamera>);
@@ -1,61 +1,300 @@
 use arrayvec::ArrayVec;
 use bevy::ecs::entity::Entity;
 use bevy::ecs::system::Commands;
-use bevy::math::{Mat4, Quat, Vec3A, Vec3};
-use bevy::render::mesh::{Indices, Mesh};
-use bevy::render::pipeline::PrimitiveTopology;
-use bevy::utils::{HashMap, HashSet};
-use hexasphere::shapes::IcoSphere;
+use bevy::math::{Mat4, Vec3A, Vec3};
+use bevy::render::draw::Visible;
+use bevy::render::mesh::Mesh;
+use bevy::utils::HashMap;
 use shaders::{PerFaceData, LowPolyMaterial, LowPolyPBRBundle};
 
 use bevy::transform::components::{GlobalTransform, Transform};
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 mod biome;
 pub mod board_ops;
 pub mod camera;
+mod mesh;
+mod neighbourhood;
+mod pathfinding;
+mod regions;
+mod topology;
 
 use bevy::prelude::BuildChildren;
 pub use biome::Biome;
 pub use board_ops::BoardPlugin;
+use board_ops::{ShellChunk, ShellLod};
+use mesh::{MaterialVertices, ShellMesh};
+use topology::ShellLayout;
+pub use regions::{BiomeRegions, Region};
+pub use topology::{BaseShape, HexSphereTopology, TileOverlaps, TopologyCache};
 use std::ops::Range;
 use bevy::asset::Assets;
-use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct NeighbourOf;
 
-#[derive(Clone, Debug, PartialEq, Default)]
+/// Every tile's neighbours, stored on the shell as compressed sparse
+/// rows: the neighbours of tile `t` are
+/// `neighbours[offsets[t]..offsets[t + 1]]`, in the same order as
+/// [`HexSphereTopology::neighbours`].
+///
+/// Tiles are identified by their [`TileIndex`], see [`Board::tiles`]
+/// for their entities.
+#[derive(Clone, Debug, PartialEq)]
 pub struct PackedRelations {
-    relations: HashMap<RelationPair, NeighbourOf>
+    // Always starts with 0, even with no tiles.
+    offsets: Vec<u32>,
+    neighbours: Vec<u32>,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct RelationPair(pub Entity, pub Entity);
+impl Default for PackedRelations {
+    fn default() -> Self {
+        Self {
+            offsets: vec![0],
+            neighbours: Vec::new(),
+        }
+    }
+}
 
-impl Hash for RelationPair {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        if self.0 > self.1 {
-            (self.0, self.1).hash(state);
-        } else {
-            (self.1, self.0).hash(state);
+impl PackedRelations {
+    pub fn new(topology: &HexSphereTopology) -> Self {
+        let mut offsets = Vec::with_capacity(topology.len() + 1);
+        let mut neighbours = Vec::with_capacity(topology.len() * 6);
+
+        offsets.push(0);
+        for tile in 0..topology.len() {
+            neighbours.extend_from_slice(topology.neighbours(tile));
+            offsets.push(neighbours.len() as u32);
+        }
+
+        Self {
+            offsets,
+            neighbours,
         }
     }
+
+    /// The number of tiles.
+    pub fn len(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn neighbours(&self, tile: usize) -> &[u32] {
+        &self.neighbours[self.offsets[tile] as usize..self.offsets[tile + 1] as usize]
+    }
+
+    /// The number of `(tile, neighbour)` entries, which counts every
+    /// pair of neighbours twice, once from each side.
+    pub fn entry_count(&self) -> usize {
+        self.neighbours.len()
+    }
+
+    /// Every `(tile, neighbour)` entry, in order, skipping the first
+    /// `start`.
+    pub fn entries_from(&self, start: usize) -> impl Iterator<Item = (u32, u32)> + '_ {
+        // No tile is without neighbours, so offsets are strictly
+        // increasing.
+        let first_tile = match self.offsets.binary_search(&(start as u32)) {
+            Ok(tile) => tile,
+            Err(next) => next - 1,
+        };
+
+        (first_tile..self.len()).flat_map(move |tile| {
+            let from = (self.offsets[tile] as usize).max(start);
+            self.neighbours[from..self.offsets[tile + 1] as usize]
+                .iter()
+                .map(move |&neighbour| (tile as u32, neighbour))
+        })
+    }
+
+    /// Every pair of neighbouring tiles, each pair listed once.
+    pub fn edges(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.entries_from(0).filter(|&(tile, neighbour)| tile < neighbour)
+    }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct PlanetDesc {
     pub subvidisions: usize,
+    pub shape: BaseShape,
     pub planet_type: BoardInitializationType,
+    pub size: ShellSize,
+    /// Raises and lowers tiles into prisms. `None` gives a smooth
+    /// sphere.
+    pub elevation: Option<ElevationParams>,
+    /// A lower detail version of the shell to show from far away.
+    pub lod: Option<LodParams>,
+    pub shading: Shading,
+    pub uv: UvParams,
+    /// Seeds the choice of each tile's variant within its biome, so
+    /// that the same description always produces the same planet, for
+    /// a given version of rand.
+    pub variant_seed: u64,
+    pub variant_selection: VariantSelection,
+}
+
+/// The index of a tile in its shell's [`HexSphereTopology`] and
+/// [`Board::tiles`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TileIndex(pub usize);
+
+/// Every tile of a shell, stored on the shell.
+#[derive(Clone, Debug)]
+pub struct Board {
+    topology: Arc<HexSphereTopology>,
+    tiles: Vec<Entity>,
+}
+
+impl Board {
+    pub fn topology(&self) -> &HexSphereTopology {
+        &self.topology
+    }
+
+    /// The tile entities, by [`TileIndex`].
+    pub fn tiles(&self) -> &[Entity] {
+        &self.tiles
+    }
+
+    /// Finds the tile that `direction` passes through. `direction` is
+    /// in the shell's local space, and need not be normalized. Gives
+    /// `None` if `direction` is zero or not finite.
+    pub fn tile_at(&self, direction: Vec3) -> Option<(TileIndex, Entity)> {
+        let idx = self.topology.locate(direction.into())?;
+        Some((TileIndex(idx), self.tiles[idx]))
+    }
+
+    /// Finds the cheapest path between two tiles, see
+    /// [`HexSphereTopology::find_path`].
+    ///
+    /// For example, to avoid lava:
+    ///
+    /// ```ignore
+    /// board.find_path(start, goal, |tile| match tiles.get(tile) {
+    ///     Ok(TileData { biome: Biome::Lava }) => None,
+    ///     _ => Some(1.0),
+    /// })
+    /// ```
+    pub fn find_path(
+        &self,
+        start: TileIndex,
+        goal: TileIndex,
+        mut cost: impl FnMut(Entity) -> Option<f32>,
+    ) -> Option<Vec<Entity>> {
+        self.topology
+            .find_path(start.0, goal.0, |tile| cost(self.tiles[tile]))
+            .map(|path| path.into_iter().map(|tile| self.tiles[tile]).collect())
+    }
+
+    /// Every tile at most `k` steps from `tile`, including `tile`
+    /// itself, see [`HexSphereTopology::tiles_within`].
+    pub fn tiles_within(&self, tile: TileIndex, k: usize) -> Vec<Entity> {
+        self.topology
+            .tiles_within(tile.0, k)
+            .into_iter()
+            .map(|tile| self.tiles[tile])
+            .collect()
+    }
+
+    /// Every tile exactly `k` steps from `tile`.
+    pub fn ring(&self, tile: TileIndex, k: usize) -> Vec<Entity> {
+        self.topology
+            .ring(tile.0, k)
+            .into_iter()
+            .map(|tile| self.tiles[tile])
+            .collect()
+    }
 }
 
-pub(crate) struct TileDataIdx(usize);
+/// The chunk of its shell a tile is in, and the vertices of the
+/// chunk's mesh which carry the tile's material.
+///
+/// Stable across runs, see [`HexSphereTopology`].
+pub(crate) struct TileDataIdx {
+    chunk: Entity,
+    vertices: MaterialVertices,
+}
 
 pub struct TileData {
     pub biome: Biome,
 }
 
+/// How far a tile is raised above (or sunk below) its shell, as a
+/// fraction of the shell's radius.
+///
+/// The tile's mesh, [`TileGeometry`] and `Transform` are built from
+/// this when the shell is spawned, so it can only be read.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileElevation(f32);
+
+impl TileElevation {
+    pub fn get(&self) -> f32 {
+        self.0
+    }
+}
+
+/// The outline of a tile, in its shell's local space (the same space
+/// as the tile's `Transform`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileGeometry {
+    /// The corners of the tile, clockwise around it in the frame of
+    /// its `Transform`, as described on [`HexSphereTopology`].
+    pub corners: ArrayVec<Vec3, 6>,
+    /// `neighbours[i]` is the tile across the edge from `corners[i]`
+    /// to `corners[i + 1]` (wrapping around). Use
+    /// [`HexSphereTopology::back_edge`] to find the way back.
+    pub neighbours: ArrayVec<Entity, 6>,
+}
+
+impl TileGeometry {
+    /// Every edge as `(start, end, neighbour)`.
+    pub fn edges(&self) -> impl Iterator<Item = (Vec3, Vec3, Entity)> + '_ {
+        self.corners
+            .iter()
+            .zip(self.corners.iter().cycle().skip(1))
+            .zip(self.neighbours.iter())
+            .map(|((&a, &b), &neighbour)| (a, b, neighbour))
+    }
+}
+
+/// The size of a tile, scaled so that the tiles of a shell add up
+/// to 4π no matter its subdivisions or radius.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileArea {
+    /// The solid angle the tile covers.
+    pub spherical: f32,
+    /// The area of the tile's flat polygon.
+    pub planar: f32,
+}
+
+/// Marks one of the five-sided tiles of a shell.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub struct Pentagon;
+
+/// The [`Pentagon`] tiles of a shell, stored on the shell. There are
+/// exactly 12 when the shell is built from an icosahedron.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Pentagons(pub Vec<Entity>);
+
+/// The number of sides of a tile, on every tile.
+///
+/// Most tiles are hexagons, but depending on the [`BaseShape`] a few
+/// have 5, 4 or 3 sides.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TileShape {
+    pub sides: u8,
+}
+
+/// Every tile of a shell which isn't a hexagon, stored on the shell.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NonHexagons(pub Vec<Entity>);
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FaceMaterialIdx(pub i32);
 
@@ -176,96 +415,230 @@ impl BoardInitializationType {
     }
 }
 
+/// How big a shell should be.
 #[derive(Copy, Clone, Debug, PartialEq)]
-pub struct BoardBuilder {
+pub enum ShellSize {
+    /// The distance from the centre of the shell to its tiles.
+    Radius(f32),
+    /// The average length of a side of a tile. The radius then grows
+    /// with the number of subdivisions.
+    EdgeLength(f32),
+}
+
+/// Drives each tile's elevation from noise.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ElevationParams {
+    pub seed: i32,
+    /// The size of features. Larger values give more, smaller, hills.
+    pub scale: f32,
+    /// The highest (and deepest) a tile can be, as a fraction of the
+    /// shell's radius.
+    pub amplitude: f32,
+}
+
+impl ElevationParams {
+    pub(crate) fn make_elevations(&self, mid_points: &[Vec3A]) -> Vec<f32> {
+        noise_gen::sample_all_noise(
+            mid_points,
+            [noise_gen::NoiseParameters {
+                scale: self.scale,
+                lac: 0.5,
+                gain: 0.5,
+                octaves: 4,
+                min: -self.amplitude,
+                max: self.amplitude,
+                seed: self.seed,
+            }],
+        )
+        .into_iter()
+        .take(mid_points.len())
+        .map(|[x]| x)
+        .collect()
+    }
+}
+
+/// Describes a shell's [`ShellLod`](board_ops::ShellLod).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LodParams {
+    /// The subdivisions of the stand-in, fewer than the shell's.
     pub subdivisions: usize,
-    pub state: BoardInitializationType,
+    /// How far the camera must be from the centre of the shell, in
+    /// multiples of its radius, before the stand-in is shown.
+    pub distance: f32,
 }
 
-impl From<PlanetDesc> for BoardBuilder {
-    fn from(x: PlanetDesc) -> Self {
+/// The size a shell ended up with, stored on the shell.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShellDimensions {
+    pub radius: f32,
+    /// The closest any part of the shell gets to its centre.
+    pub inner_radius: f32,
+    /// The average length of a side of a tile.
+    pub mean_edge_length: f32,
+}
+
+/// How a shell's surface is lit.
+///
+/// Every mesh carries normals regardless, so that it can be exported;
+/// this only decides which normals they are and whether the material
+/// uses them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Shading {
+    /// Each triangle is lit by its own facet, worked out from the
+    /// positions in the shader.
+    Faceted,
+    /// Each tile is lit as one flat face, with flat walls if the shell
+    /// is elevated.
+    Flat,
+    /// The surface is lit as a smooth sphere. Suited to sky and cloud
+    /// shells.
+    Smooth,
+}
+
+impl Default for Shading {
+    fn default() -> Self {
+        Shading::Faceted
+    }
+}
+
+/// How the texture coordinates of a shell's mesh are laid out.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum UvMapping {
+    /// By latitude and longitude. Simple, but squashed at the poles.
+    Equirectangular,
+    /// Projected from the faces of a cube, so each face gets its own
+    /// copy of the texture.
+    Cube,
+    /// Every tile gets its own copy of the texture, centred on the
+    /// tile and lined up with its [`Transform`].
+    TileLocal,
+}
+
+impl Default for UvMapping {
+    fn default() -> Self {
+        UvMapping::Equirectangular
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UvParams {
+    pub mapping: UvMapping,
+    /// How many times the texture repeats across the mapping: around
+    /// the poles, across a cube face or across a tile respectively.
+    pub tiling: f32,
+}
+
+impl Default for UvParams {
+    fn default() -> Self {
         Self {
-            subdivisions: x.subvidisions,
-            state: x.planet_type,
+            mapping: UvMapping::Equirectangular,
+            tiling: 10.0,
         }
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Hash)]
-enum Index {
-    All(u32),
-    Mid(u32),
+/// How a tile picks which of its biome's variants (such as the three
+/// kinds of dirt) to use.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum VariantSelection {
+    /// Every tile picks independently.
+    Random,
+    /// Variants follow a noise field, so neighbouring tiles of the
+    /// same biome tend to share a variant and form patches.
+    Noise,
 }
 
-impl Index {
-    pub fn resolve(self, mid: u32) -> u32 {
+impl Default for VariantSelection {
+    fn default() -> Self {
+        VariantSelection::Random
+    }
+}
+
+impl VariantSelection {
+    pub(crate) fn select(
+        &self,
+        mid_points: &[Vec3A],
+        biomes: &[Biome],
+        hmap: &HashMap<Biome, Range<i32>>,
+        seed: u64,
+    ) -> Vec<i32> {
         match self {
-            Self::All(x) => x,
-            Self::Mid(x) => x + mid,
+            VariantSelection::Random => {
+                // `StdRng`'s algorithm may change between versions of
+                // rand, so a fixed one is used instead. The way
+                // `gen_range` maps its output into a range is still tied
+                // to the version of rand, so upgrading it may change
+                // planets.
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+                biomes
+                    .iter()
+                    .map(|biome| rng.gen_range(hmap.get(biome).unwrap().clone()))
+                    .collect()
+            },
+            VariantSelection::Noise => {
+                let noise = noise_gen::sample_all_noise(
+                    mid_points,
+                    [noise_gen::NoiseParameters {
+                        scale: 4.0,
+                        lac: 0.5,
+                        gain: 0.5,
+                        octaves: 2,
+                        min: 0.0,
+                        max: 1.0,
+                        seed: seed as i32,
+                    }],
+                );
+
+                biomes
+                    .iter()
+                    .zip(noise.into_iter())
+                    .map(|(biome, [x])| {
+                        let range = hmap.get(biome).unwrap();
+                        let len = range.end - range.start;
+                        // x can be exactly 1.0 at the noise's maximum.
+                        range.start + ((x * len as f32) as i32).min(len - 1)
+                    })
+                    .collect()
+            },
         }
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-struct SurroundingEntry {
-    edge: [u32; 2],
-    avg_idx: u32,
-}
-
-fn add_chunk_triangle_indices(
-    a: u32,
-    b: u32,
-    c: u32,
-    unordered_edges: &mut HashMap<(u32, u32), NeighbourOf>,
-    original_points: &[Vec3A],
-    new_points: &mut Vec<Vec3A>,
-    per_face_indices: &mut Vec<i32>,
-    surrounding_points: &mut HashMap<u32, ArrayVec<SurroundingEntry, 6>>,
-) {
-    unordered_edges.extend(std::array::IntoIter::new([
-        ((a.min(b), a.max(b)), NeighbourOf),
-        ((b.min(c), b.max(c)), NeighbourOf),
-        ((c.min(a), c.max(a)), NeighbourOf),
-    ]));
-
-    let avg =
-        (original_points[a as usize] + original_points[b as usize] + original_points[c as usize])
-            / 3.0;
-    let avg_idx = new_points.len() as u32;
-    new_points.push(avg);
-    per_face_indices.push(0);
-
-    surrounding_points
-        .entry(a)
-        .or_insert_with(ArrayVec::new)
-        .push(SurroundingEntry {
-            edge: [b, c],
-            avg_idx,
-        });
-
-    surrounding_points
-        .entry(b)
-        .or_insert_with(ArrayVec::new)
-        .push(SurroundingEntry {
-            edge: [c, a],
-            avg_idx,
-        });
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoardBuilder {
+    pub subdivisions: usize,
+    pub shape: BaseShape,
+    pub state: BoardInitializationType,
+    pub size: ShellSize,
+    pub elevation: Option<ElevationParams>,
+    pub lod: Option<LodParams>,
+    pub shading: Shading,
+    pub uv: UvParams,
+    pub variant_seed: u64,
+    pub variant_selection: VariantSelection,
+}
 
-    surrounding_points
-        .entry(c)
-        .or_insert_with(ArrayVec::new)
-        .push(SurroundingEntry {
-            edge: [a, b],
-            avg_idx,
-        });
+impl From<PlanetDesc> for BoardBuilder {
+    fn from(x: PlanetDesc) -> Self {
+        Self {
+            subdivisions: x.subvidisions,
+            shape: x.shape,
+            state: x.planet_type,
+            size: x.size,
+            elevation: x.elevation,
+            lod: x.lod,
+            shading: x.shading,
+            uv: x.uv,
+            variant_seed: x.variant_seed,
+            variant_selection: x.variant_selection,
+        }
+    }
 }
 
 fn make_point_transform(normalized_point: Vec3A) -> Transform {
     // normalized_point is the new "y"
-
-    let x = (Quat::from_rotation_y(0.1) * normalized_point).normalize();
-    let z = normalized_point.cross(x).normalize();
-    let x = normalized_point.cross(z).normalize();
+    let (x, z) = topology::tangent_frame(normalized_point);
 
     Transform::from_matrix(Mat4::from_cols(
         x.extend(0.0),
@@ -276,215 +649,413 @@ fn make_point_transform(normalized_point: Vec3A) -> Transform {
 }
 
 impl BoardBuilder {
+    /// How many times [`build`](Self::build) increments its progress.
+    pub const BUILD_STEPS: usize = 5;
+
+    fn make_mesh(
+        &self,
+        topology: &HexSphereTopology,
+        elevations: &[f32],
+        tiles: &[usize],
+    ) -> ShellMesh {
+        // Flat tiles and per tile UVs can't share corners with their
+        // neighbours.
+        let separate_tiles = self.elevation.is_some()
+            || self.shading == Shading::Flat
+            || self.uv.mapping == UvMapping::TileLocal;
+
+        if separate_tiles {
+            ShellMesh::extruded(topology, elevations, tiles, self.shading == Shading::Smooth)
+        } else {
+            ShellMesh::flat(topology, tiles)
+        }
+    }
+
+    /// Builds the shell and spawns it on `board` straight away.
+    ///
+    /// This blocks until the shell is built; see [`build`](Self::build)
+    /// to do the expensive parts elsewhere.
     pub fn create_on(
         &self,
         commands: &mut Commands,
         board: Entity,
         meshes: &mut Assets<Mesh>,
         planet_materials: &mut Assets<LowPolyMaterial>,
+        topologies: &TopologyCache,
     ) {
-        let sphere = IcoSphere::new(self.subdivisions, |_| ());
-        let original_points = sphere.raw_points();
-        // Keep the middle points and the between-points separate
-        // since we only need most of the tile parameters for the
-        // middle points. All points need a
-        // Every point other than the middle ones
-        let mut new_points = Vec::new();
-        // The middle points
-        let mut mid_points = Vec::new();
-
-        // Index into the per-hexagon material.
-        let mut per_face_indices = Vec::new();
-        let mut mid_face_indices = Vec::new();
-
-        // This is re-used per main chunk.
-        // It contains, for each point (key), every pair of neighbours (.edge)
-        // and the index of the point in the middle of the triangle (.avg_idx)
-        let mut surrounding_points: HashMap<u32, ArrayVec<SurroundingEntry, 6>> =
-            HashMap::default();
-
-        // Stores all of the indices of the new geometry.
-        // The elements are either:
-        // All(x) -> x is an index into the edge points.
-        // Mid(x) -> x is an index into the middle points and should
-        //           be added to the number of edge points when resolved.
-        let mut temp_out_indices =
-            Vec::with_capacity(((original_points.len() - 12) * 6 + (12 * 5)) * 3);
-
-        let mut entities = Vec::new();
-
-        // Graph Edge creation stuff:
-        //
-        // Edges from center id to center id.
-        let mut unordered_edges = HashMap::default();
-        // Center id to node index.
-        let mut old_center_to_node = HashMap::default();
-
-        let old_indices = sphere.get_all_indices();
-
-        // I chose 6 & 7 arbitrarily, trying to avoid pentagons and
-        // mostly scale hexagons.
-        let scale_factor = 1.0 / (original_points[old_indices[6] as usize] - original_points[old_indices[7] as usize]).length();
-
-        for triangle in old_indices.chunks(3) {
-            add_chunk_triangle_indices(
-                triangle[0],
-                triangle[1],
-                triangle[2],
-                &mut unordered_edges,
-                &original_points,
-                &mut new_points,
-                &mut per_face_indices,
-                &mut surrounding_points,
-            );
-        }
+        let parts = self.build(topologies, &AtomicUsize::new(0));
+        self.spawn(parts, commands, board, meshes, planet_materials);
+    }
 
-        // For every full hexagon we get...
-        for (old_center, sides) in surrounding_points.iter() {
-            let mut sides = sides.clone();
-            let center = mid_points.len();
-            let entity = commands.spawn().id();
-            old_center_to_node.insert(*old_center, entity);
-
-            let mut ordered_points = ArrayVec::<usize, 6>::new();
-
-            let SurroundingEntry {
-                edge: [first, mut next],
-                avg_idx: f_data,
-            } = sides.pop().unwrap();
-
-            ordered_points.push(f_data as usize);
-            while sides.len() > 0 {
-                let SurroundingEntry {
-                    edge: [_, n],
-                    avg_idx: data,
-                } = sides.remove(
-                    sides
-                        .iter()
-                        .position(|SurroundingEntry { edge: [x, _], .. }| *x == next)
-                        .unwrap(),
-                );
-                next = n;
-                ordered_points.push(data as _);
-            }
-            assert_eq!(next, first);
+    /// Works out everything about the shell which doesn't need the
+    /// `World`: its topology, noise, biomes and meshes.
+    ///
+    /// Topologies, and whatever else only depends on the subdivisions
+    /// and shape, are taken from `topologies` when they've been built
+    /// before.
+    ///
+    /// `progress` is incremented once for each of the
+    /// [`BUILD_STEPS`](Self::BUILD_STEPS) finished, so that it can be
+    /// watched from another thread.
+    pub fn build(&self, topologies: &TopologyCache, progress: &AtomicUsize) -> ShellParts {
+        let step = || {
+            progress.fetch_add(1, Ordering::Relaxed);
+        };
 
-            let avg = ordered_points
-                .iter()
-                .fold(Vec3A::ZERO, |prev, idx| prev + new_points[*idx])
-                .normalize();
+        let layout = topologies.layout(self.subdivisions, self.shape);
+        let topology = &layout.topology;
+        step();
 
-            mid_points.push(avg);
+        let elevations = match self.elevation {
+            Some(params) => params.make_elevations(topology.centres()),
+            None => vec![0.0; topology.len()],
+        };
 
-            let mut iter = ordered_points.iter().copied().peekable();
-            while let Some(a) = iter.next() {
-                use Index::*;
+        let (biomes, hmap, per_face_data) = self.state.make_biomes(topology.centres());
 
-                let b = *iter.peek().unwrap_or(&ordered_points[0]);
-                temp_out_indices.extend_from_slice(&[
-                    Mid(center as u32),
-                    All(a as u32),
-                    All(b as u32),
-                ]);
-            }
+        let variants = self.variant_selection.select(
+            topology.centres(),
+            &biomes,
+            hmap,
+            self.variant_seed,
+        );
+        step();
+
+        let regions = BiomeRegions::new(topology, biomes[..topology.len()].to_vec());
+        step();
+
+        let chunk_meshes = layout
+            .chunks
+            .iter()
+            .map(|tiles| self.make_mesh(topology, &elevations, tiles))
+            .collect::<Vec<_>>();
 
-            mid_face_indices.push(1);
+        let inner_radius = chunk_meshes
+            .iter()
+            .map(ShellMesh::inner_radius)
+            .fold(f32::INFINITY, f32::min);
 
-            entities.push(entity);
+        let chunks = chunk_meshes
+            .into_iter()
+            .map(|mesh| {
+                let (axis, spread, height) = mesh.bounds();
+                ChunkParts {
+                    mesh: mesh.to_mesh(self.uv),
+                    material_vertices: mesh.material_vertices,
+                    axis,
+                    spread,
+                    height,
+                }
+            })
+            .collect();
+        step();
+
+        let proxy = self.lod.map(|params| {
+            let proxy_topology = topologies.get(params.subdivisions, self.shape);
+            let proxy_tiles = (0..proxy_topology.len()).collect::<Vec<_>>();
+            let proxy_mesh = self.make_mesh(
+                &proxy_topology,
+                &vec![0.0; proxy_topology.len()],
+                &proxy_tiles,
+            );
+
+            ProxyParts {
+                mesh: proxy_mesh.to_mesh(self.uv),
+                overlaps: topologies.overlaps(
+                    (params.subdivisions, self.shape),
+                    (self.subdivisions, self.shape),
+                ),
+                material_vertices: proxy_mesh.material_vertices,
+            }
+        });
+        step();
+
+        ShellParts {
+            layout,
+            elevations,
+            biomes,
+            variants,
+            per_face_data,
+            regions,
+            chunks,
+            inner_radius,
+            proxy,
         }
+    }
 
-        let packed_relations = PackedRelations {
-            relations: unordered_edges
-                .into_iter()
-                .map(|((edge_a, edge_b), relation_data)| {
-                    let a = *old_center_to_node.get(&edge_a).unwrap();
-                    let b = *old_center_to_node.get(&edge_b).unwrap();
+    /// Spawns the tiles, chunks and proxy of a shell [`build`](Self::build)
+    /// made, and inserts the shell's components on `board`.
+    pub fn spawn(
+        &self,
+        parts: ShellParts,
+        commands: &mut Commands,
+        board: Entity,
+        meshes: &mut Assets<Mesh>,
+        planet_materials: &mut Assets<LowPolyMaterial>,
+    ) {
+        let ShellParts {
+            layout,
+            elevations,
+            biomes,
+            variants,
+            per_face_data,
+            regions,
+            chunks: chunk_parts,
+            inner_radius,
+            proxy,
+        } = parts;
+        let topology = &layout.topology;
+
+        let entities = (0..topology.len())
+            .map(|_| commands.spawn().id())
+            .collect::<Vec<_>>();
 
-                    (RelationPair(a, b), relation_data)
-                })
-                .collect()
-        };
+        let chunks = chunk_parts
+            .iter()
+            .map(|_| commands.spawn().id())
+            .collect::<Vec<_>>();
+
+        let mut tile_data_idx = (0..topology.len()).map(|_| None).collect::<Vec<_>>();
+        for ((&chunk, parts), tiles) in chunks.iter().zip(&chunk_parts).zip(&layout.chunks) {
+            for (&tile, vertices) in tiles.iter().zip(&parts.material_vertices) {
+                tile_data_idx[tile] = Some(TileDataIdx {
+                    chunk,
+                    vertices: vertices.clone(),
+                });
+            }
+        }
+
+        let packed_relations = PackedRelations::new(topology);
 
-        let (biomes, hmap, per_face_data) = self.state.make_biomes(&mid_points);
+        let pentagons = topology
+            .pentagons()
+            .map(|tile| {
+                commands.entity(entities[tile]).insert(Pentagon);
+                entities[tile]
+            })
+            .collect::<Vec<Entity>>();
 
-        let mut rng = rand::thread_rng();
+        let non_hexagons = topology
+            .non_hexagons()
+            .map(|tile| entities[tile])
+            .collect::<Vec<Entity>>();
 
         entities
             .iter()
-            .zip(biomes.into_iter())
+            .zip(biomes.into_iter().zip(variants.into_iter()))
             .enumerate()
-            .for_each(|(idx, (&entity, biome))| {
-                let biome_idx = rng.gen_range(hmap.get(&biome).unwrap().clone());
+            .for_each(|(idx, (&entity, (biome, biome_idx)))| {
+                let height = 1.0 + elevations[idx];
+
+                let geometry = TileGeometry {
+                    corners: topology
+                        .tile_corners(idx)
+                        .iter()
+                        .map(|&corner| {
+                            (topology.corner_points()[corner as usize] * height).into()
+                        })
+                        .collect(),
+                    neighbours: topology
+                        .neighbours(idx)
+                        .iter()
+                        .map(|&neighbour| entities[neighbour as usize])
+                        .collect(),
+                };
+
+                let mut transform = make_point_transform(topology.centres()[idx]);
+                transform.translation *= height;
 
                 commands.entity(entity).insert_bundle((
                     GlobalTransform::default(),
-                    make_point_transform(mid_points[idx]),
+                    transform,
+                    geometry,
                     FaceMaterialIdx(biome_idx),
                     OldFaceMaterialIdx(biome_idx),
                     TileData { biome },
-                    TileDataIdx(idx + per_face_indices.len()),
+                    TileElevation(elevations[idx]),
+                    TileIndex(idx),
+                    TileShape {
+                        sides: topology.sides(idx) as u8,
+                    },
+                    TileArea {
+                        spherical: layout.spherical_areas[idx],
+                        planar: layout.planar_areas[idx],
+                    },
+                    tile_data_idx[idx].take().unwrap(),
                 ));
             });
 
-        // Resolve real indices.
-        let mid = new_points.len() as u32;
-        let indices = temp_out_indices
-            .into_iter()
-            .map(|x| x.resolve(mid))
-            .collect::<Vec<_>>();
-        new_points.extend(mid_points.into_iter());
-        per_face_indices.extend(mid_face_indices.into_iter());
-
-        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-
-        mesh.set_indices(Some(Indices::U32(indices)));
-        mesh.set_attribute(
-            Mesh::ATTRIBUTE_UV_0,
-            new_points
-                .iter()
-                .map(|point| {
-                    let inclination = point.y.acos();
-                    let azimuth = point.z.atan2(point.x);
-
-                    let norm_inclination = inclination / std::f32::consts::PI;
-                    let norm_azimuth = 0.5 - (azimuth / std::f32::consts::TAU);
-
-                    let incl_factor = 1.0 - ((norm_inclination - 0.5) * (norm_inclination - 0.5));
+        let unit_edge_length = topology.mean_edge_length();
+        let radius = match self.size {
+            ShellSize::Radius(radius) => radius,
+            ShellSize::EdgeLength(length) => length / unit_edge_length,
+        };
 
-                    const TILING: f32 = 10.0;
+        let material = planet_materials.add(LowPolyMaterial {
+            per_face_data,
+            double_sided: true,
+            vertex_normals: self.shading != Shading::Faceted,
+            ..Default::default()
+        });
 
-                    [
-                        norm_azimuth * TILING * 2.0 * incl_factor.powf(1.0 / 2.40942),
-                        norm_inclination * TILING,
-                    ]
+        for ((&chunk, parts), tiles) in chunks.iter().zip(chunk_parts).zip(&layout.chunks) {
+            commands
+                .entity(chunk)
+                .insert_bundle(LowPolyPBRBundle {
+                    mesh: meshes.add(parts.mesh),
+                    material: material.clone(),
+                    ..Default::default()
                 })
-                .collect::<Vec<[f32; 2]>>(),
-        );
-        mesh.set_attribute(
-            Mesh::ATTRIBUTE_POSITION,
-            new_points
-                .into_iter()
-                .map(|p| [p.x, p.y, p.z])
-                .collect::<Vec<[_; 3]>>(),
-        );
-        mesh.set_attribute(shaders::ATTRIBUTE_PER_FACE_INDEX, per_face_indices);
+                .insert(ShellChunk {
+                    tiles: tiles.iter().map(|&tile| entities[tile]).collect(),
+                    axis: parts.axis.into(),
+                    spread: parts.spread,
+                    height: parts.height,
+                });
+        }
 
-        println!("{:?}", scale_factor);
+        // The proxy's colours are filled in from the tiles under it once
+        // they've been added.
+        if let (Some(params), Some(proxy_parts)) = (self.lod, proxy) {
+            let proxy = commands
+                .spawn_bundle(LowPolyPBRBundle {
+                    mesh: meshes.add(proxy_parts.mesh),
+                    material: material.clone(),
+                    visible: Visible {
+                        is_visible: false,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .id();
+
+            commands
+                .entity(board)
+                .push_children(&[proxy])
+                .insert(ShellLod {
+                    proxy,
+                    distance: params.distance,
+                    active: false,
+                    overlaps: proxy_parts.overlaps,
+                    proxy_vertices: proxy_parts.material_vertices,
+                });
+        }
 
         commands
             .entity(board)
             //TODO: https://github.com/OptimisticPeach/hexagonfactory/issues/2
             .push_children(&entities)
-            .insert_bundle(LowPolyPBRBundle {
-                mesh: meshes.add(mesh),
-                material: planet_materials.add(LowPolyMaterial {
-                    per_face_data,
-                    double_sided: true,
-                    ..Default::default()
-                }),
-                transform: Transform::from_scale(Vec3::splat(scale_factor)),
-                ..Default::default()
+            .push_children(&chunks)
+            .insert_bundle((
+                Transform::from_scale(Vec3::splat(radius)),
+                GlobalTransform::default(),
+            ))
+            .insert(packed_relations)
+            .insert(ShellDimensions {
+                radius,
+                inner_radius: inner_radius * radius,
+                mean_edge_length: unit_edge_length * radius,
             })
-            .insert(packed_relations);
+            .insert(Pentagons(pentagons))
+            .insert(NonHexagons(non_hexagons))
+            .insert(regions)
+            .insert(Board {
+                topology: topology.clone(),
+                tiles: entities,
+            });
+    }
+}
+
+/// A shell made by [`BoardBuilder::build`], ready to be
+/// [spawned](BoardBuilder::spawn).
+pub struct ShellParts {
+    layout: Arc<ShellLayout>,
+    elevations: Vec<f32>,
+    biomes: Vec<Biome>,
+    variants: Vec<i32>,
+    per_face_data: Vec<PerFaceData>,
+    regions: BiomeRegions,
+    chunks: Vec<ChunkParts>,
+    inner_radius: f32,
+    proxy: Option<ProxyParts>,
+}
+
+// In the same order as the layout's chunks.
+struct ChunkParts {
+    mesh: Mesh,
+    material_vertices: Vec<MaterialVertices>,
+    axis: Vec3A,
+    spread: f32,
+    height: f32,
+}
+
+struct ProxyParts {
+    mesh: Mesh,
+    overlaps: Arc<TileOverlaps>,
+    material_vertices: Vec<MaterialVertices>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_resume_mid_row() {
+        let relations = PackedRelations::new(&HexSphereTopology::new(3));
+        let all = relations.entries_from(0).collect::<Vec<_>>();
+        assert_eq!(all.len(), relations.entry_count());
+        assert_eq!(relations.edges().count(), all.len() / 2);
+
+        // Every start, most of which are part way through a tile's
+        // neighbours.
+        for start in 0..=all.len() {
+            assert_eq!(
+                relations.entries_from(start).collect::<Vec<_>>(),
+                all[start..].to_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn default_relations_are_empty() {
+        let relations = PackedRelations::default();
+        assert!(relations.is_empty());
+        assert_eq!(relations.entries_from(0).count(), 0);
+        assert_eq!(relations.edges().count(), 0);
+    }
+
+    fn variant_inputs() -> (Vec<Vec3A>, Vec<Biome>) {
+        let topology = HexSphereTopology::new(8);
+        let biomes = (0..topology.len())
+            .map(|tile| [Biome::Dirt, Biome::Metal, Biome::Lava, Biome::Ice][tile % 4])
+            .collect();
+        (topology.centres().to_vec(), biomes)
+    }
+
+    #[test]
+    fn random_variants_are_seeded() {
+        let (centres, biomes) = variant_inputs();
+        let hmap = &*crate::biome::BASE_BIOME_MAP;
+        let select = |seed| VariantSelection::Random.select(&centres, &biomes, hmap, seed);
+
+        let variants = select(5);
+        assert_eq!(variants, select(5));
+        assert_ne!(variants, select(6));
+        for (biome, variant) in biomes.iter().zip(variants) {
+            assert!(hmap[biome].contains(&variant));
+        }
+    }
+
+    #[test]
+    fn noise_variants_stay_in_range() {
+        let (centres, biomes) = variant_inputs();
+        let hmap = &*crate::biome::BASE_BIOME_MAP;
+        let select = |seed| VariantSelection::Noise.select(&centres, &biomes, hmap, seed);
+
+        let variants = select(5);
+        assert_eq!(variants, select(5));
+        for (biome, variant) in biomes.iter().zip(variants) {
+            assert!(hmap[biome].contains(&variant));
+        }
     }
 }
@@ -0,0 +1,346 @@
+use crate::{make_point_transform, HexSphereTopology, UvMapping, UvParams};
+use bevy::math::{Vec3, Vec3A};
+use bevy::render::mesh::{Indices, Mesh};
+use bevy::render::pipeline::PrimitiveTopology;
+use bevy::utils::HashMap;
+use smallvec::SmallVec;
+use std::ops::Range;
+
+/// The geometry of a shell, on the unit sphere.
+pub(crate) struct ShellMesh {
+    pub positions: Vec<Vec3A>,
+    pub normals: Vec<Vec3A>,
+    pub indices: Vec<u32>,
+    // Index into the per-hexagon material.
+    pub per_face_indices: Vec<i32>,
+    // Every vertex of each tile, some of which may be shared with
+    // other tiles.
+    pub tile_vertices: Vec<Range<usize>>,
+    // The vertices carrying each tile's per face index.
+    pub material_vertices: Vec<MaterialVertices>,
+}
+
+/// The vertices of a mesh which start one of a tile's triangles, and
+/// so decide its material. The rest stay at 0, so that the shading
+/// can fade towards the tile's edges.
+pub(crate) type MaterialVertices = SmallVec<[u32; 7]>;
+
+impl ShellMesh {
+    /// A faceted sphere, where neighbouring tiles share corners.
+    ///
+    /// Since corners are shared, the normals are smooth across tiles.
+    pub fn flat(topology: &HexSphereTopology, tiles: &[usize]) -> Self {
+        // Corners are shared between chunks, so renumber them.
+        let mut local_corners = HashMap::default();
+        let mut positions = Vec::new();
+        for &tile in tiles {
+            for &corner in topology.tile_corners(tile) {
+                local_corners.entry(corner).or_insert_with(|| {
+                    positions.push(topology.corner_points()[corner as usize]);
+                    (positions.len() - 1) as u32
+                });
+            }
+        }
+        let corner_count = positions.len();
+
+        // The middle points come after all of the corners.
+        let mid = corner_count as u32;
+        let mut indices = Vec::with_capacity(tiles.len() * 6 * 3);
+        for (i, &tile) in tiles.iter().enumerate() {
+            let corners = topology.tile_corners(tile);
+            let mut iter = corners.iter().map(|x| local_corners[x]).peekable();
+            let first = local_corners[&corners[0]];
+            // Corners go clockwise, so reverse them to face outwards.
+            while let Some(a) = iter.next() {
+                let b = *iter.peek().unwrap_or(&first);
+                indices.extend_from_slice(&[mid + i as u32, b, a]);
+            }
+        }
+
+        positions.extend(tiles.iter().map(|&tile| topology.centres()[tile]));
+
+        // Only the middle points belong to a tile. They're the first
+        // vertex of each triangle, so they decide its material.
+        let per_face_indices = std::iter::repeat(0)
+            .take(corner_count)
+            .chain(std::iter::repeat(1).take(tiles.len()))
+            .collect::<Vec<i32>>();
+
+        let tile_vertices = (corner_count..corner_count + tiles.len())
+            .map(|x| x..x + 1)
+            .collect::<Vec<_>>();
+        let material_vertices = tile_vertices
+            .iter()
+            .map(|x| std::iter::once(x.start as u32).collect())
+            .collect();
+
+        let normals = positions.iter().map(|x| x.normalize()).collect();
+
+        Self {
+            positions,
+            normals,
+            indices,
+            per_face_indices,
+            tile_vertices,
+            material_vertices,
+        }
+    }
+
+    /// Every tile is a prism raised by `1.0 + elevations[tile]`, with
+    /// walls down to any lower neighbours.
+    ///
+    /// No vertices are shared between tiles, so that the walls can
+    /// take on the material of the tile they belong to. The top of each
+    /// tile is lit as one flat face, unless `smooth` is set, in which
+    /// case it's lit as part of a sphere. Walls are always flat.
+    pub fn extruded(
+        topology: &HexSphereTopology,
+        elevations: &[f32],
+        tiles: &[usize],
+        smooth: bool,
+    ) -> Self {
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut indices = Vec::new();
+        let mut tile_vertices = Vec::with_capacity(tiles.len());
+        let mut material_vertices = Vec::with_capacity(tiles.len());
+
+        for &tile in tiles {
+            let start = positions.len();
+            let height = 1.0 + elevations[tile];
+            let corners = topology.tile_corners(tile);
+            let corner = |i: usize| topology.corner_points()[corners[i % corners.len()] as usize];
+
+            let centre = topology.centres()[tile];
+            positions.push(centre * height);
+            positions.extend((0..corners.len()).map(|i| corner(i) * height));
+            normals.push(centre);
+            normals.extend((0..corners.len()).map(|i| match smooth {
+                true => corner(i).normalize(),
+                false => centre,
+            }));
+
+            let s = start as u32;
+            let mut starts = MaterialVertices::new();
+            starts.push(s);
+            for i in 0..corners.len() as u32 {
+                let next = (i + 1) % corners.len() as u32;
+                indices.extend_from_slice(&[s, s + 1 + next, s + 1 + i]);
+            }
+
+            for (i, &neighbour) in topology.neighbours(tile).iter().enumerate() {
+                let lower = 1.0 + elevations[neighbour as usize];
+                // The neighbour builds the wall if it's the higher one.
+                if lower >= height {
+                    continue;
+                }
+
+                // Corners go clockwise, so `a`, `b`, then down to `b`
+                // is anticlockwise seen from outside the tile.
+                let (a, b) = (corner(i), corner(i + 1));
+                let w = positions.len() as u32;
+                positions.extend_from_slice(&[a * height, b * height, b * lower, a * lower]);
+
+                // The wall lies in the plane through a, b and the origin,
+                // and faces away from the tile.
+                let mut normal = a.cross(b).normalize();
+                if normal.dot(centre) > 0.0 {
+                    normal = -normal;
+                }
+                normals.extend_from_slice(&[normal; 4]);
+                indices.extend_from_slice(&[w, w + 1, w + 2, w, w + 2, w + 3]);
+                starts.push(w);
+            }
+
+            tile_vertices.push(start..positions.len());
+            material_vertices.push(starts);
+        }
+
+        // As in `flat`, only the vertices starting a triangle belong to
+        // a tile.
+        let mut per_face_indices = vec![0; positions.len()];
+        for &vertex in material_vertices.iter().flatten() {
+            per_face_indices[vertex as usize] = 1;
+        }
+
+        Self {
+            positions,
+            normals,
+            indices,
+            per_face_indices,
+            tile_vertices,
+            material_vertices,
+        }
+    }
+
+    /// The radius of the largest sphere around the origin which fits
+    /// entirely within the mesh.
+    pub fn inner_radius(&self) -> f32 {
+        self.indices
+            .chunks(3)
+            .map(|triangle| {
+                distance_to_triangle(
+                    self.positions[triangle[0] as usize],
+                    self.positions[triangle[1] as usize],
+                    self.positions[triangle[2] as usize],
+                )
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// A cone from the origin containing every vertex, as its axis and
+    /// half angle, along with the furthest any vertex is from the
+    /// origin.
+    pub fn bounds(&self) -> (Vec3A, f32, f32) {
+        let axis = self
+            .positions
+            .iter()
+            .fold(Vec3A::ZERO, |acc, x| acc + x.normalize())
+            .normalize();
+
+        let (spread, furthest) = self
+            .positions
+            .iter()
+            .fold((0.0f32, 0.0f32), |(spread, furthest), x| {
+                let angle = axis.dot(x.normalize()).max(-1.0).min(1.0).acos();
+                (spread.max(angle), furthest.max(x.length()))
+            });
+
+        (axis, spread, furthest)
+    }
+
+    fn uvs(&self, uv: UvParams) -> Vec<[f32; 2]> {
+        let tiling = uv.tiling;
+        match uv.mapping {
+            UvMapping::Equirectangular => self
+                .positions
+                .iter()
+                .map(|point| {
+                    let point = point.normalize();
+                    let inclination = point.y.acos();
+                    let azimuth = point.z.atan2(point.x);
+
+                    let norm_inclination = inclination / std::f32::consts::PI;
+                    let norm_azimuth = 0.5 - (azimuth / std::f32::consts::TAU);
+
+                    let incl_factor = 1.0 - ((norm_inclination - 0.5) * (norm_inclination - 0.5));
+
+                    [
+                        norm_azimuth * tiling * 2.0 * incl_factor.powf(1.0 / 2.40942),
+                        norm_inclination * tiling,
+                    ]
+                })
+                .collect(),
+            UvMapping::Cube => self
+                .positions
+                .iter()
+                .map(|point| {
+                    let abs = point.abs();
+                    // Project onto the face of the cube the point is
+                    // facing, oriented the same way as a cube map.
+                    let (u, v, major) = if abs.x >= abs.y && abs.x >= abs.z {
+                        (-point.z * point.x.signum(), -point.y, abs.x)
+                    } else if abs.y >= abs.z {
+                        (point.x, point.z * point.y.signum(), abs.y)
+                    } else {
+                        (point.x * point.z.signum(), -point.y, abs.z)
+                    };
+
+                    [
+                        (u / major + 1.0) * 0.5 * tiling,
+                        (v / major + 1.0) * 0.5 * tiling,
+                    ]
+                })
+                .collect(),
+            UvMapping::TileLocal => {
+                let mut uvs = vec![[0.0; 2]; self.positions.len()];
+                for range in &self.tile_vertices {
+                    // The first vertex of a tile is its middle.
+                    let centre = self.positions[range.start];
+                    let transform = make_point_transform(centre.normalize());
+                    let x = Vec3A::from(transform.rotation * Vec3::X);
+                    let z = Vec3A::from(transform.rotation * Vec3::Z);
+
+                    let offsets = self.positions[range.clone()]
+                        .iter()
+                        .map(|&point| {
+                            let offset = point - centre;
+                            offset.dot(x).abs().max(offset.dot(z).abs())
+                        })
+                        .fold(0.0f32, f32::max);
+                    let scale = if offsets == 0.0 { 0.0 } else { 0.5 / offsets };
+
+                    for i in range.clone() {
+                        let offset = self.positions[i] - centre;
+                        uvs[i] = [
+                            (0.5 + offset.dot(x) * scale) * tiling,
+                            (0.5 + offset.dot(z) * scale) * tiling,
+                        ];
+                    }
+                }
+                uvs
+            }
+        }
+    }
+
+    /// `uv`'s [`UvMapping::TileLocal`] needs every tile to have its own
+    /// vertices, as in [`ShellMesh::extruded`].
+    pub fn to_mesh(&self, uv: UvParams) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+
+        mesh.set_indices(Some(Indices::U32(self.indices.clone())));
+        mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs(uv));
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            self.positions
+                .iter()
+                .map(|p| [p.x, p.y, p.z])
+                .collect::<Vec<[_; 3]>>(),
+        );
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            self.normals
+                .iter()
+                .map(|n| [n.x, n.y, n.z])
+                .collect::<Vec<[_; 3]>>(),
+        );
+        mesh.set_attribute(
+            shaders::ATTRIBUTE_PER_FACE_INDEX,
+            self.per_face_indices.clone(),
+        );
+
+        mesh
+    }
+}
+
+// The distance from the origin to the closest point of the triangle.
+//
+// Walls lie in planes through the origin, so the distance to the
+// triangle's plane alone isn't enough.
+fn distance_to_triangle(a: Vec3A, b: Vec3A, c: Vec3A) -> f32 {
+    let normal = (b - a).cross(c - a);
+    let edges = [(a, b), (b, c), (c, a)];
+
+    // The origin is over the inside of the triangle if it's on the
+    // inner side of every edge.
+    let inside = normal.length_squared() > 0.0
+        && edges
+            .iter()
+            .all(|&(p, q)| (q - p).cross(-p).dot(normal) >= 0.0);
+
+    if inside {
+        normal.normalize().dot(a).abs()
+    } else {
+        edges
+            .iter()
+            .map(|&(p, q)| {
+                let edge = q - p;
+                let t = match edge.length_squared() {
+                    x if x == 0.0 => 0.0,
+                    x => (-p).dot(edge) / x,
+                };
+                (p + edge * t.max(0.0).min(1.0)).length()
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+}
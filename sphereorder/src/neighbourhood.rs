@@ -0,0 +1,81 @@
+use crate::HexSphereTopology;
+use bevy::utils::HashSet;
+
+impl HexSphereTopology {
+    // rings[i] holds the tiles exactly i steps from `tile`.
+    fn rings(&self, tile: usize, k: usize) -> Vec<Vec<usize>> {
+        let mut visited = HashSet::default();
+        visited.insert(tile);
+
+        let mut rings = vec![vec![tile]];
+        for _ in 0..k {
+            let next = rings
+                .last()
+                .unwrap()
+                .iter()
+                .flat_map(|&x| self.neighbours(x).iter().map(|&x| x as usize))
+                .filter(|&x| visited.insert(x))
+                .collect::<Vec<_>>();
+
+            rings.push(next);
+        }
+
+        rings
+    }
+
+    /// Every tile at most `k` steps from `tile`, including `tile`
+    /// itself, ordered by the number of steps.
+    ///
+    /// Steps are counted along the tile graph, so this is correct
+    /// around pentagons, where rings have fewer tiles than on a flat
+    /// hex grid.
+    pub fn tiles_within(&self, tile: usize, k: usize) -> Vec<usize> {
+        self.rings(tile, k).into_iter().flatten().collect()
+    }
+
+    /// Every tile exactly `k` steps from `tile`.
+    pub fn ring(&self, tile: usize, k: usize) -> Vec<usize> {
+        self.rings(tile, k).pop().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::HexSphereTopology;
+
+    #[test]
+    fn rings_around_a_pentagon() {
+        let topology = HexSphereTopology::new(8);
+        let pentagon = topology.pentagons().next().unwrap();
+        for k in 1..4 {
+            assert_eq!(topology.ring(pentagon, k).len(), 5 * k);
+            assert_eq!(
+                topology.tiles_within(pentagon, k).len(),
+                1 + 5 * k * (k + 1) / 2
+            );
+        }
+    }
+
+    #[test]
+    fn rings_around_a_hexagon() {
+        let topology = HexSphereTopology::new(8);
+        // Far enough from every pentagon that the rings are flat.
+        let hexagon = (0..topology.len())
+            .find(|&tile| {
+                topology
+                    .tiles_within(tile, 3)
+                    .iter()
+                    .all(|&x| !topology.is_pentagon(x))
+            })
+            .unwrap();
+
+        for k in 1..4 {
+            assert_eq!(topology.ring(hexagon, k).len(), 6 * k);
+            assert_eq!(
+                topology.tiles_within(hexagon, k).len(),
+                1 + 3 * k * (k + 1)
+            );
+        }
+        assert_eq!(topology.ring(hexagon, 0), vec![hexagon]);
+    }
+}
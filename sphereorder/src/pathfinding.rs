@@ -0,0 +1,156 @@
+use crate::HexSphereTopology;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Candidate {
+    // Cost so far plus the heuristic.
+    estimate: f32,
+    tile: u32,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, since `BinaryHeap` is a max-heap.
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl HexSphereTopology {
+    /// The great circle distance between the centres of two tiles, on
+    /// the unit sphere.
+    pub fn distance(&self, a: usize, b: usize) -> f32 {
+        self.centres()[a]
+            .dot(self.centres()[b])
+            .max(-1.0)
+            .min(1.0)
+            .acos()
+    }
+
+    /// Finds the cheapest path from `start` to `goal` using A*.
+    ///
+    /// Moving into a tile costs the distance travelled times
+    /// `cost(tile)`. Tiles for which `cost` returns `None` can't be
+    /// entered. Costs should be at least `1.0`, otherwise the path may
+    /// not be the cheapest one.
+    ///
+    /// Returns every tile along the way, including `start` and `goal`.
+    pub fn find_path(
+        &self,
+        start: usize,
+        goal: usize,
+        mut cost: impl FnMut(usize) -> Option<f32>,
+    ) -> Option<Vec<usize>> {
+        let mut best = vec![f32::INFINITY; self.len()];
+        let mut came_from = vec![u32::MAX; self.len()];
+        let mut open = BinaryHeap::new();
+
+        best[start] = 0.0;
+        open.push(Candidate {
+            estimate: self.distance(start, goal),
+            tile: start as u32,
+        });
+
+        while let Some(Candidate { estimate, tile }) = open.pop() {
+            let tile = tile as usize;
+
+            if tile == goal {
+                let mut path = vec![goal];
+                while *path.last().unwrap() != start {
+                    path.push(came_from[*path.last().unwrap()] as usize);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            // We've since found a cheaper way here.
+            if estimate > best[tile] + self.distance(tile, goal) {
+                continue;
+            }
+
+            for &next in self.neighbours(tile) {
+                let next = next as usize;
+                let factor = if let Some(x) = cost(next) {
+                    x
+                } else {
+                    continue;
+                };
+
+                let through = best[tile] + self.distance(tile, next) * factor;
+                if through < best[next] {
+                    best[next] = through;
+                    came_from[next] = tile as u32;
+                    open.push(Candidate {
+                        estimate: through + self.distance(next, goal),
+                        tile: next as u32,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::HexSphereTopology;
+
+    fn assert_connected(topology: &HexSphereTopology, path: &[usize]) {
+        for step in path.windows(2) {
+            assert!(topology.edge_towards(step[0], step[1]).is_some());
+        }
+    }
+
+    #[test]
+    fn paths_are_connected() {
+        let topology = HexSphereTopology::new(8);
+        let goal = topology.len() / 2;
+
+        assert_eq!(topology.find_path(3, 3, |_| Some(1.0)), Some(vec![3]));
+
+        let path = topology.find_path(0, goal, |_| Some(1.0)).unwrap();
+        assert_eq!((path[0], *path.last().unwrap()), (0, goal));
+        assert_connected(&topology, &path);
+    }
+
+    #[test]
+    fn paths_avoid_blocked_tiles() {
+        let topology = HexSphereTopology::new(8);
+        let goal = topology.len() / 2;
+        let direct = topology.find_path(0, goal, |_| Some(1.0)).unwrap();
+
+        // Block the middle of the direct path.
+        let blocked = direct[direct.len() / 2];
+        let path = topology
+            .find_path(0, goal, |tile| if tile == blocked { None } else { Some(1.0) })
+            .unwrap();
+        assert!(!path.contains(&blocked));
+        assert!(path.len() >= direct.len());
+        assert_connected(&topology, &path);
+
+        // Surround the goal.
+        let walls = topology.neighbours(goal).to_vec();
+        assert_eq!(
+            topology.find_path(0, goal, |tile| {
+                if walls.contains(&(tile as u32)) {
+                    None
+                } else {
+                    Some(1.0)
+                }
+            }),
+            None
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{Biome, HexSphereTopology};
+use smallvec::SmallVec;
+
+const UNASSIGNED: u32 = u32::MAX;
+
+/// A connected group of tiles sharing a biome, such as an island of
+/// cloud or a field of lava.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub biome: Biome,
+    /// Every tile in the region.
+    pub tiles: Vec<u32>,
+    /// The tiles in the region which touch a tile of another biome.
+    pub border: Vec<u32>,
+}
+
+impl Region {
+    pub fn size(&self) -> usize {
+        self.tiles.len()
+    }
+}
+
+/// The [`Region`]s of a shell, stored on the shell.
+///
+/// Region ids are only stable until the next change: regions which
+/// split or merge are renumbered.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BiomeRegions {
+    biomes: Vec<Biome>,
+    region_of: Vec<u32>,
+    regions: Vec<Option<Region>>,
+    free: Vec<u32>,
+}
+
+impl BiomeRegions {
+    pub fn new(topology: &HexSphereTopology, biomes: Vec<Biome>) -> Self {
+        let mut regions = Self {
+            region_of: vec![UNASSIGNED; biomes.len()],
+            biomes,
+            regions: Vec::new(),
+            free: Vec::new(),
+        };
+
+        for tile in 0..topology.len() {
+            if regions.region_of[tile] == UNASSIGNED {
+                regions.add_region(topology, tile);
+            }
+        }
+
+        regions
+    }
+
+    // Flood fills from `start` over unassigned tiles of the same biome.
+    fn add_region(&mut self, topology: &HexSphereTopology, start: usize) {
+        let id = self.free.pop().unwrap_or_else(|| {
+            self.regions.push(None);
+            (self.regions.len() - 1) as u32
+        });
+        let biome = self.biomes[start];
+
+        let mut tiles = vec![start as u32];
+        self.region_of[start] = id;
+
+        let mut next = 0;
+        while next < tiles.len() {
+            let tile = tiles[next] as usize;
+            next += 1;
+
+            for &neighbour in topology.neighbours(tile) {
+                let neighbour = neighbour as usize;
+                if self.region_of[neighbour] == UNASSIGNED && self.biomes[neighbour] == biome {
+                    self.region_of[neighbour] = id;
+                    tiles.push(neighbour as u32);
+                }
+            }
+        }
+
+        let border = tiles
+            .iter()
+            .copied()
+            .filter(|&tile| {
+                topology
+                    .neighbours(tile as usize)
+                    .iter()
+                    .any(|&neighbour| self.biomes[neighbour as usize] != biome)
+            })
+            .collect();
+
+        self.regions[id as usize] = Some(Region {
+            biome,
+            tiles,
+            border,
+        });
+    }
+
+    /// The id of the region `tile` is in.
+    pub fn region_of(&self, tile: usize) -> usize {
+        self.region_of[tile] as usize
+    }
+
+    pub fn region(&self, id: usize) -> &Region {
+        self.regions[id].as_ref().unwrap()
+    }
+
+    /// Every region, with its id.
+    pub fn regions(&self) -> impl Iterator<Item = (usize, &Region)> {
+        self.regions
+            .iter()
+            .enumerate()
+            .filter_map(|(id, region)| region.as_ref().map(|region| (id, region)))
+    }
+
+    /// Every region of a given biome, with its id.
+    pub fn regions_of(&self, biome: Biome) -> impl Iterator<Item = (usize, &Region)> {
+        self.regions().filter(move |(_, region)| region.biome == biome)
+    }
+
+    /// Updates the regions after `tile` changed to `biome`.
+    ///
+    /// Only the regions touching `tile` are rebuilt, so this costs
+    /// about as much as their total size.
+    pub fn set_biome(&mut self, topology: &HexSphereTopology, tile: usize, biome: Biome) {
+        if self.biomes[tile] == biome {
+            return;
+        }
+        self.biomes[tile] = biome;
+
+        // The old region may split, and neighbouring regions may merge
+        // or gain border tiles.
+        let mut affected = std::iter::once(self.region_of[tile])
+            .chain(
+                topology
+                    .neighbours(tile)
+                    .iter()
+                    .map(|&x| self.region_of[x as usize]),
+            )
+            .collect::<SmallVec<[u32; 7]>>();
+        affected.sort_unstable();
+        affected.dedup();
+
+        let tiles = affected
+            .iter()
+            .flat_map(|&id| self.regions[id as usize].take().unwrap().tiles)
+            .collect::<Vec<_>>();
+
+        self.free.extend(affected);
+        tiles
+            .iter()
+            .for_each(|&tile| self.region_of[tile as usize] = UNASSIGNED);
+
+        for tile in tiles {
+            if self.region_of[tile as usize] == UNASSIGNED {
+                self.add_region(topology, tile as usize);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn set_biome_matches_new() {
+        // Region ids may differ, so compare the regions themselves.
+        fn sorted(regions: &BiomeRegions) -> Vec<(Biome, Vec<u32>, Vec<u32>)> {
+            let mut regions = regions
+                .regions()
+                .map(|(_, region)| {
+                    let mut tiles = region.tiles.clone();
+                    let mut border = region.border.clone();
+                    tiles.sort_unstable();
+                    border.sort_unstable();
+                    (region.biome, tiles, border)
+                })
+                .collect::<Vec<_>>();
+            regions.sort_unstable_by(|a, b| a.1.cmp(&b.1));
+            regions
+        }
+
+        let pick = |rng: &mut ChaCha8Rng| match rng.gen_range(0..3) {
+            0 => Biome::Dirt,
+            1 => Biome::Lava,
+            _ => Biome::Ice,
+        };
+
+        let topology = HexSphereTopology::new(8);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut biomes = (0..topology.len())
+            .map(|_| pick(&mut rng))
+            .collect::<Vec<_>>();
+        let mut regions = BiomeRegions::new(&topology, biomes.clone());
+
+        for _ in 0..1000 {
+            let tile = rng.gen_range(0..topology.len());
+            let biome = pick(&mut rng);
+            biomes[tile] = biome;
+            regions.set_biome(&topology, tile, biome);
+        }
+
+        assert_eq!(
+            sorted(&regions),
+            sorted(&BiomeRegions::new(&topology, biomes))
+        );
+    }
+}
@@ -0,0 +1,933 @@
+use arrayvec::ArrayVec;
+use bevy::math::{Quat, Vec3A};
+use bevy::utils::HashMap;
+use hexasphere::shapes::{CubeSphere, IcoSphere, NormIcoSphere, TetraSphere};
+use hexasphere::Subdivided;
+use parking_lot::Mutex;
+use smallvec::SmallVec;
+use std::sync::Arc;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct SurroundingEntry {
+    edge: [u32; 2],
+    avg_idx: u32,
+}
+
+fn add_chunk_triangle_indices(
+    a: u32,
+    b: u32,
+    c: u32,
+    original_points: &[Vec3A],
+    corners: &mut Vec<Vec3A>,
+    surrounding_points: &mut [ArrayVec<SurroundingEntry, 6>],
+) {
+    let avg =
+        (original_points[a as usize] + original_points[b as usize] + original_points[c as usize])
+            / 3.0;
+    let avg_idx = corners.len() as u32;
+    corners.push(avg);
+
+    surrounding_points[a as usize].push(SurroundingEntry {
+        edge: [b, c],
+        avg_idx,
+    });
+
+    surrounding_points[b as usize].push(SurroundingEntry {
+        edge: [c, a],
+        avg_idx,
+    });
+
+    surrounding_points[c as usize].push(SurroundingEntry {
+        edge: [a, b],
+        avg_idx,
+    });
+}
+
+/// Walks the triangles surrounding a point so that consecutive
+/// triangles share an edge.
+///
+/// Returns the corner indices in order, and for each corner `i`
+/// the original point on the other side of the edge from corner
+/// `i` to corner `i + 1`.
+fn order_ring(mut sides: ArrayVec<SurroundingEntry, 6>) -> (ArrayVec<u32, 6>, ArrayVec<u32, 6>) {
+    let mut ordered_points = ArrayVec::new();
+    let mut ordered_neighbours = ArrayVec::new();
+
+    let SurroundingEntry {
+        edge: [first, mut next],
+        avg_idx: f_data,
+    } = sides.pop().unwrap();
+
+    ordered_points.push(f_data);
+    ordered_neighbours.push(next);
+    while sides.len() > 0 {
+        let SurroundingEntry {
+            edge: [_, n],
+            avg_idx: data,
+        } = sides.remove(
+            sides
+                .iter()
+                .position(|SurroundingEntry { edge: [x, _], .. }| *x == next)
+                .unwrap(),
+        );
+        next = n;
+        ordered_points.push(data);
+        ordered_neighbours.push(n);
+    }
+    assert_eq!(next, first);
+
+    (ordered_points, ordered_neighbours)
+}
+
+/// The `x` and `z` axes of the frame a tile is oriented by, where
+/// `normal` is its `y` axis. Tiles' `Transform`s use the same frame.
+pub(crate) fn tangent_frame(normal: Vec3A) -> (Vec3A, Vec3A) {
+    let x = (Quat::from_rotation_y(0.1) * normal).normalize();
+    let z = normal.cross(x).normalize();
+    let x = normal.cross(z).normalize();
+    (x, z)
+}
+
+// Puts a ring from `order_ring` in clockwise order, as seen from
+// outside the sphere, starting from the edge whose middle is first
+// clockwise of the `x` axis of `tangent_frame`.
+//
+// Clockwise from above `y` turns `x` towards `z`.
+fn orient_ring(
+    corners: &[Vec3A],
+    centre: Vec3A,
+    ring: &mut ArrayVec<u32, 6>,
+    neighbours: &mut ArrayVec<u32, 6>,
+) {
+    let len = ring.len();
+    let corner = |ring: &[u32], i: usize| corners[ring[i % len] as usize] - centre;
+
+    if corner(ring, 0).cross(corner(ring, 1)).dot(centre) > 0.0 {
+        // The edge from corner `i` to corner `i + 1` becomes the edge
+        // from corner `len - 2 - i` to `len - 1 - i`.
+        ring.reverse();
+        let old = neighbours.clone();
+        for i in 0..len {
+            neighbours[i] = old[(2 * len - 2 - i) % len];
+        }
+    }
+
+    let (x, z) = tangent_frame(centre);
+    let angle = |i: usize| {
+        let middle = corner(ring, i) + corner(ring, i + 1);
+        let angle = middle.dot(z).atan2(middle.dot(x));
+        if angle < 0.0 {
+            angle + std::f32::consts::TAU
+        } else {
+            angle
+        }
+    };
+
+    let first = (0..len)
+        .map(|i| (i, angle(i)))
+        .fold(None, |acc: Option<(usize, f32)>, (i, angle)| match acc {
+            Some((_, best)) if best <= angle => acc,
+            _ => Some((i, angle)),
+        })
+        .unwrap()
+        .0;
+
+    ring.rotate_left(first);
+    neighbours.rotate_left(first);
+}
+
+/// The polyhedron a [`HexSphereTopology`] is subdivided from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BaseShape {
+    /// The most uniform tiles, with exactly 12 pentagons.
+    Icosahedron,
+    /// An icosahedron whose points are spread by normalizing rather
+    /// than slerping. Quicker to build, but tiles vary more in size.
+    NormalizedIcosahedron,
+    /// Has 4 triangular tiles, and is badly skewed below about 10
+    /// subdivisions.
+    Tetrahedron,
+    /// Has 4 four-sided tiles and 4 pentagons at the corners of the
+    /// cube.
+    Cube,
+}
+
+impl Default for BaseShape {
+    fn default() -> Self {
+        BaseShape::Icosahedron
+    }
+}
+
+/// The hexagon/pentagon tiling of a subdivided polyhedron, an
+/// icosahedron unless built with [`with_shape`](Self::with_shape).
+///
+/// This is plain data: it knows nothing of entities, meshes or
+/// the `World`, so it can be built and queried anywhere.
+///
+/// Tiles are identified by their index, in `0..len()`. Every tile
+/// has 6 corners and 6 neighbours, apart from a few where the
+/// vertices of the polyhedron were: exactly 12 pentagons on an
+/// icosahedron, and tiles with 3 to 5 corners on other shapes.
+///
+/// Each tile's corners and neighbours go clockwise, seen from outside
+/// the sphere, in the frame of the tile's `Transform`. Edge `0` is the
+/// first edge clockwise of the frame's `x` axis, and edge `i + 1` is
+/// one step clockwise of edge `i`.
+///
+/// The ordering is stable: tile `n` is built around point `n` of
+/// the underlying subdivided shape, so for a given shape and
+/// subdivision count the same index always refers to the same tile,
+/// as do the corner indices and the order of each tile's corners and
+/// neighbours. Nothing here depends on hashing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HexSphereTopology {
+    // The points shared between tiles. These are not normalized.
+    corners: Vec<Vec3A>,
+    // The normalized middle point of each tile.
+    centres: Vec<Vec3A>,
+    // Indices into `corners`, in order around each tile.
+    tile_corners: Vec<ArrayVec<u32, 6>>,
+    // `neighbours[t][i]` is the tile across the edge from
+    // `tile_corners[t][i]` to `tile_corners[t][i + 1]`.
+    neighbours: Vec<ArrayVec<u32, 6>>,
+    // `back_edges[t][i]` is the edge of `neighbours[t][i]` which
+    // borders `t`.
+    back_edges: Vec<ArrayVec<u8, 6>>,
+    // A tile near the middle of each cell of a cube wrapped around the
+    // sphere, used as a starting point by `locate`.
+    locator: CubeBuckets,
+}
+
+/// Splits each face of a cube into `resolution * resolution` cells.
+#[derive(Clone, Debug, PartialEq)]
+struct CubeBuckets {
+    resolution: usize,
+    seeds: Vec<u32>,
+}
+
+impl CubeBuckets {
+    fn cell(&self, direction: Vec3A) -> usize {
+        let abs = direction.abs();
+        // Pick the face the direction points through, then the
+        // other two axes are the position on that face.
+        let (face, u, v, major) = if abs.x >= abs.y && abs.x >= abs.z {
+            (0, direction.y, direction.z, direction.x)
+        } else if abs.y >= abs.z {
+            (2, direction.z, direction.x, direction.y)
+        } else {
+            (4, direction.x, direction.y, direction.z)
+        };
+        let face = face + (major < 0.0) as usize;
+
+        let to_cell = |x: f32| {
+            let x = (x / major.abs() + 1.0) * 0.5 * self.resolution as f32;
+            (x.max(0.0) as usize).min(self.resolution - 1)
+        };
+
+        (face * self.resolution + to_cell(u)) * self.resolution + to_cell(v)
+    }
+
+    fn cell_centre(&self, cell: usize) -> Vec3A {
+        let v = cell % self.resolution;
+        let u = (cell / self.resolution) % self.resolution;
+        let face = cell / (self.resolution * self.resolution);
+
+        let from_cell = |x: usize| ((x as f32 + 0.5) / self.resolution as f32) * 2.0 - 1.0;
+        let (u, v) = (from_cell(u), from_cell(v));
+        let major = if face % 2 == 0 { 1.0 } else { -1.0 };
+
+        match face / 2 {
+            0 => Vec3A::new(major, u, v),
+            1 => Vec3A::new(v, major, u),
+            _ => Vec3A::new(u, v, major),
+        }
+        .normalize()
+    }
+}
+
+impl HexSphereTopology {
+    pub fn new(subdivisions: usize) -> Self {
+        Self::with_shape(subdivisions, BaseShape::Icosahedron)
+    }
+
+    pub fn with_shape(subdivisions: usize, shape: BaseShape) -> Self {
+        match shape {
+            BaseShape::Icosahedron => {
+                Self::from_sphere(&IcoSphere::new(subdivisions, |_| ()), subdivisions)
+            }
+            BaseShape::NormalizedIcosahedron => {
+                Self::from_sphere(&NormIcoSphere::new(subdivisions, |_| ()), subdivisions)
+            }
+            BaseShape::Tetrahedron => {
+                Self::from_sphere(&TetraSphere::new(subdivisions, |_| ()), subdivisions)
+            }
+            BaseShape::Cube => {
+                Self::from_sphere(&CubeSphere::new(subdivisions, |_| ()), subdivisions)
+            }
+        }
+    }
+
+    fn from_sphere<S: hexasphere::BaseShape>(
+        sphere: &Subdivided<(), S>,
+        subdivisions: usize,
+    ) -> Self {
+        let original_points = sphere.raw_points();
+        let old_indices = sphere.get_all_indices();
+
+        let mut corners = Vec::with_capacity(old_indices.len() / 3);
+
+        // It contains, for each point (by index), every pair of neighbours (.edge)
+        // and the index of the point in the middle of the triangle (.avg_idx)
+        let mut surrounding_points =
+            vec![ArrayVec::<SurroundingEntry, 6>::new(); original_points.len()];
+
+        for triangle in old_indices.chunks(3) {
+            add_chunk_triangle_indices(
+                triangle[0],
+                triangle[1],
+                triangle[2],
+                &original_points,
+                &mut corners,
+                &mut surrounding_points,
+            );
+        }
+
+        let mut centres = Vec::with_capacity(surrounding_points.len());
+        let mut tile_corners = Vec::with_capacity(surrounding_points.len());
+        let mut neighbours = Vec::with_capacity(surrounding_points.len());
+
+        // Every original point becomes the centre of one tile, with the
+        // same index. Neighbours are therefore already tile indices.
+        for sides in surrounding_points.into_iter() {
+            let (mut ordered_points, mut ordered_neighbours) = order_ring(sides);
+
+            let avg = ordered_points
+                .iter()
+                .fold(Vec3A::ZERO, |prev, idx| prev + corners[*idx as usize])
+                .normalize();
+
+            orient_ring(&corners, avg, &mut ordered_points, &mut ordered_neighbours);
+
+            centres.push(avg);
+            tile_corners.push(ordered_points);
+            neighbours.push(ordered_neighbours);
+        }
+
+        let back_edges = neighbours
+            .iter()
+            .enumerate()
+            .map(|(tile, ring)| {
+                ring.iter()
+                    .map(|&neighbour| {
+                        neighbours[neighbour as usize]
+                            .iter()
+                            .position(|&x| x as usize == tile)
+                            .unwrap() as u8
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut topology = Self {
+            corners,
+            centres,
+            tile_corners,
+            neighbours,
+            back_edges,
+            locator: CubeBuckets {
+                // Roughly one cell per tile.
+                resolution: subdivisions + 1,
+                seeds: Vec::new(),
+            },
+        };
+
+        let cells = topology.locator.resolution * topology.locator.resolution * 6;
+        let mut seed = 0;
+        // Neighbouring cells are close, so each walk is short.
+        topology.locator.seeds = (0..cells)
+            .map(|cell| {
+                seed = topology.closest_from(topology.locator.cell_centre(cell), seed);
+                seed as u32
+            })
+            .collect();
+
+        topology
+    }
+
+    // Walks towards the tile whose centre is closest to `direction`.
+    // Every step gets strictly closer, so this terminates for any
+    // finite `direction`, but it's capped in case of floating point
+    // trouble all the same.
+    fn closest_from(&self, direction: Vec3A, mut tile: usize) -> usize {
+        let mut best = self.centres[tile].dot(direction);
+        for _ in 0..self.len() {
+            let next = self.neighbours[tile]
+                .iter()
+                .map(|&x| (x as usize, self.centres[x as usize].dot(direction)))
+                .fold(None, |acc: Option<(usize, f32)>, (x, dot)| match acc {
+                    Some((_, acc_dot)) if acc_dot >= dot => acc,
+                    _ => Some((x, dot)),
+                })
+                .unwrap();
+
+            if next.1 <= best {
+                return tile;
+            }
+
+            tile = next.0;
+            best = next.1;
+        }
+
+        tile
+    }
+
+    /// Finds the tile that `direction` (from the centre of the sphere)
+    /// passes through. `direction` need not be normalized, but gives
+    /// `None` if it's zero or not finite.
+    ///
+    /// This takes constant time: it starts from a precomputed tile near
+    /// `direction` and walks only a few steps.
+    pub fn locate(&self, direction: Vec3A) -> Option<usize> {
+        let length_squared = direction.length_squared();
+        if length_squared == 0.0 || !length_squared.is_finite() {
+            return None;
+        }
+
+        let direction = direction / length_squared.sqrt();
+        let mut tile = self.closest_from(
+            direction,
+            self.locator.seeds[self.locator.cell(direction)] as usize,
+        );
+
+        // The closest centre is almost always the right tile, but tiles
+        // aren't exactly the Voronoi cells of their centres. Step across
+        // any edge `direction` is outside of. This is bounded in case
+        // of floating point trouble right on a corner.
+        for _ in 0..4 {
+            let corners = &self.tile_corners[tile];
+            let centre = self.centres[tile];
+            let outside = (0..corners.len()).find(|&i| {
+                let a = self.corners[corners[i] as usize];
+                let b = self.corners[corners[(i + 1) % corners.len()] as usize];
+                let normal = a.cross(b);
+                normal.dot(direction) * normal.dot(centre) < 0.0
+            });
+
+            match outside {
+                Some(edge) => tile = self.neighbours[tile][edge] as usize,
+                None => break,
+            }
+        }
+
+        Some(tile)
+    }
+
+    /// The number of tiles.
+    pub fn len(&self) -> usize {
+        self.centres.len()
+    }
+
+    /// Always false, every subdivision of every shape has tiles.
+    pub fn is_empty(&self) -> bool {
+        self.centres.is_empty()
+    }
+
+    /// The normalized centre of every tile.
+    pub fn centres(&self) -> &[Vec3A] {
+        &self.centres
+    }
+
+    /// Every corner point, shared between up to three tiles.
+    pub fn corner_points(&self) -> &[Vec3A] {
+        &self.corners
+    }
+
+    /// Indices into [`corner_points`](Self::corner_points), in order
+    /// around `tile`.
+    pub fn tile_corners(&self, tile: usize) -> &[u32] {
+        &self.tile_corners[tile]
+    }
+
+    /// The tiles bordering `tile`, clockwise. The `i`th neighbour is
+    /// across the edge from corner `i` to corner `i + 1`.
+    pub fn neighbours(&self, tile: usize) -> &[u32] {
+        &self.neighbours[tile]
+    }
+
+    /// The edge of the tile across `edge` of `tile` which leads back
+    /// to `tile`.
+    pub fn back_edge(&self, tile: usize, edge: usize) -> usize {
+        self.back_edges[tile][edge] as usize
+    }
+
+    /// The edge of `tile` bordering `neighbour`, if they're neighbours.
+    pub fn edge_towards(&self, tile: usize, neighbour: usize) -> Option<usize> {
+        self.neighbours[tile]
+            .iter()
+            .position(|&x| x as usize == neighbour)
+    }
+
+    /// The edge `steps` clockwise of `edge` on `tile`. Negative steps
+    /// turn anticlockwise.
+    pub fn turn(&self, tile: usize, edge: usize, steps: isize) -> usize {
+        let len = self.neighbours[tile].len() as isize;
+        (edge as isize + steps).rem_euclid(len) as usize
+    }
+
+    /// The number of corners, and so neighbours, of `tile`. This is 6
+    /// apart from the tiles around the corners of the base shape: 5 for
+    /// an icosahedron, 4 or 5 for a cube and 3 for a tetrahedron.
+    pub fn sides(&self, tile: usize) -> usize {
+        self.tile_corners[tile].len()
+    }
+
+    pub fn is_pentagon(&self, tile: usize) -> bool {
+        self.sides(tile) == 5
+    }
+
+    /// The indices of the five-sided tiles, of which an icosahedron
+    /// has exactly 12.
+    pub fn pentagons(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).filter(move |&tile| self.is_pentagon(tile))
+    }
+
+    /// The indices of the tiles with fewer than six sides.
+    pub fn non_hexagons(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).filter(move |&tile| self.sides(tile) != 6)
+    }
+
+    /// Every pair of neighbouring tiles, each pair listed once.
+    pub fn edges(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.neighbours
+            .iter()
+            .enumerate()
+            .flat_map(|(tile, ring)| {
+                let tile = tile as u32;
+                ring.iter()
+                    .copied()
+                    .filter(move |&other| tile < other)
+                    .map(move |other| (tile, other))
+            })
+    }
+
+    // The triangles (centre, corner i, corner i + 1) making up a tile.
+    fn fan(&self, tile: usize) -> impl Iterator<Item = (Vec3A, Vec3A, Vec3A)> + '_ {
+        let corners = &self.tile_corners[tile];
+        let centre = self.centres[tile];
+        (0..corners.len()).map(move |i| {
+            (
+                centre,
+                self.corners[corners[i] as usize],
+                self.corners[corners[(i + 1) % corners.len()] as usize],
+            )
+        })
+    }
+
+    /// The solid angle covered by `tile`, with its corners projected
+    /// onto the unit sphere. The tiles of a shell add up to 4π.
+    pub fn spherical_area(&self, tile: usize) -> f32 {
+        self.fan(tile)
+            .map(|(a, b, c)| {
+                let (a, b, c) = (a.normalize(), b.normalize(), c.normalize());
+                // Van Oosterom & Strackee's formula for the solid angle
+                // of a triangle.
+                let numerator = a.dot(b.cross(c)).abs();
+                let denominator = 1.0 + a.dot(b) + b.dot(c) + c.dot(a);
+                2.0 * numerator.atan2(denominator)
+            })
+            .sum()
+    }
+
+    /// The flat area of `tile`, as it is meshed on the unit sphere.
+    pub fn planar_area(&self, tile: usize) -> f32 {
+        self.fan(tile)
+            .map(|(a, b, c)| (b - a).cross(c - a).length() * 0.5)
+            .sum()
+    }
+
+    /// The average length of a tile's side, on the unit sphere.
+    pub fn mean_edge_length(&self) -> f32 {
+        let (total, count) = (0..self.len())
+            .flat_map(|tile| self.fan(tile))
+            .fold((0.0, 0), |(total, count), (_, a, b)| {
+                (total + (a - b).length(), count + 1)
+            });
+
+        total / count as f32
+    }
+}
+
+// The subdivisions of the sphere whose tiles decide the chunks of a
+// shell's mesh. 1 gives 42 chunks.
+const CHUNK_SUBDIVISIONS: usize = 1;
+
+/// What every shell of one size and shape shares besides its
+/// topology.
+#[derive(Debug)]
+pub(crate) struct ShellLayout {
+    pub(crate) topology: Arc<HexSphereTopology>,
+    // Normalized to sum to 4π, like `TileArea`.
+    pub(crate) spherical_areas: Vec<f32>,
+    pub(crate) planar_areas: Vec<f32>,
+    // The tiles under each tile of a much coarser sphere, leaving out
+    // the ones with no tiles under them.
+    pub(crate) chunks: Vec<Vec<usize>>,
+}
+
+impl ShellLayout {
+    fn new(topology: Arc<HexSphereTopology>, chunk_topology: &HexSphereTopology) -> Self {
+        let normalized = |areas: Vec<f32>| {
+            let scale = 4.0 * std::f32::consts::PI / areas.iter().sum::<f32>();
+            areas.into_iter().map(|x| x * scale).collect()
+        };
+
+        let spherical_areas = normalized(
+            (0..topology.len())
+                .map(|tile| topology.spherical_area(tile))
+                .collect(),
+        );
+        let planar_areas = normalized(
+            (0..topology.len())
+                .map(|tile| topology.planar_area(tile))
+                .collect(),
+        );
+
+        let mut chunks = vec![Vec::new(); chunk_topology.len()];
+        for tile in 0..topology.len() {
+            chunks[chunk_topology.locate(topology.centres[tile]).unwrap()].push(tile);
+        }
+        chunks.retain(|tiles| !tiles.is_empty());
+
+        Self {
+            topology,
+            spherical_areas,
+            planar_areas,
+            chunks,
+        }
+    }
+}
+
+type Shared<K, V> = Arc<Mutex<HashMap<K, Arc<V>>>>;
+
+// Don't hold the lock while building, other shapes and sizes can
+// still be looked up.
+fn get_or_build<K: Eq + std::hash::Hash, V>(
+    map: &Mutex<HashMap<K, Arc<V>>>,
+    key: K,
+    build: impl FnOnce() -> V,
+) -> Arc<V> {
+    if let Some(value) = map.lock().get(&key) {
+        return value.clone();
+    }
+
+    let value = Arc::new(build());
+
+    map.lock().entry(key).or_insert(value).clone()
+}
+
+/// Every [`HexSphereTopology`] built so far, by subdivisions and
+/// shape, so that shells of the same size can share one. Whatever else
+/// only depends on the size and shape of a shell, such as its tile
+/// areas, is kept alongside.
+///
+/// Clones refer to the same cache, so it can be handed to other
+/// threads. Two threads asking for the same missing topology at once
+/// may both build it, but only the first to finish is kept.
+#[derive(Clone, Debug, Default)]
+pub struct TopologyCache {
+    topologies: Shared<(usize, BaseShape), HexSphereTopology>,
+    layouts: Shared<(usize, BaseShape), ShellLayout>,
+    overlaps: Shared<((usize, BaseShape), (usize, BaseShape)), TileOverlaps>,
+}
+
+impl TopologyCache {
+    pub fn get(&self, subdivisions: usize, shape: BaseShape) -> Arc<HexSphereTopology> {
+        get_or_build(&self.topologies, (subdivisions, shape), || {
+            HexSphereTopology::with_shape(subdivisions, shape)
+        })
+    }
+
+    pub(crate) fn layout(&self, subdivisions: usize, shape: BaseShape) -> Arc<ShellLayout> {
+        get_or_build(&self.layouts, (subdivisions, shape), || {
+            ShellLayout::new(
+                self.get(subdivisions, shape),
+                &self.get(CHUNK_SUBDIVISIONS, BaseShape::Icosahedron),
+            )
+        })
+    }
+
+    /// How the tiles of two topologies overlap, each given by its
+    /// subdivisions and shape.
+    pub fn overlaps(
+        &self,
+        lower: (usize, BaseShape),
+        upper: (usize, BaseShape),
+    ) -> Arc<TileOverlaps> {
+        get_or_build(&self.overlaps, (lower, upper), || {
+            TileOverlaps::new(&self.get(lower.0, lower.1), &self.get(upper.0, upper.1))
+        })
+    }
+
+    /// Forgets everything. Shells already using a topology keep it.
+    pub fn clear(&self) {
+        self.topologies.lock().clear();
+        self.layouts.lock().clear();
+        self.overlaps.lock().clear();
+    }
+}
+
+/// How the tiles of two shells overlap, when both are projected
+/// onto the unit sphere.
+///
+/// The areas are estimated by sampling each lower tile, so very thin
+/// slivers of overlap may be missed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileOverlaps {
+    // For each lower tile: (upper tile, fraction of the lower tile).
+    above: Vec<SmallVec<[(u32, f32); 8]>>,
+    // For each upper tile: (lower tile, fraction of the upper tile).
+    below: Vec<SmallVec<[(u32, f32); 8]>>,
+}
+
+impl TileOverlaps {
+    pub fn new(lower: &HexSphereTopology, upper: &HexSphereTopology) -> Self {
+        // Each triangle of a tile's fan is split into `samples^2`
+        // pieces, about 50 for each upper tile, so that even upper tiles
+        // much smaller than the lower ones are sampled.
+        let ratio = upper.len() as f32 / lower.len() as f32;
+        let samples = 3 * ratio.sqrt().ceil().max(1.0) as usize;
+
+        // Absolute areas until the end.
+        let mut above = vec![SmallVec::<[(u32, f32); 8]>::new(); lower.len()];
+        let mut lower_areas = vec![0.0; lower.len()];
+        let mut upper_areas = vec![0.0; upper.len()];
+
+        for tile in 0..lower.len() {
+            let centre = lower.centres[tile];
+            let corners = &lower.tile_corners[tile];
+
+            for i in 0..corners.len() {
+                let a = lower.corners[corners[i] as usize].normalize();
+                let b = lower.corners[corners[(i + 1) % corners.len()] as usize].normalize();
+                let area = (a - centre).cross(b - centre).length() * 0.5;
+                let piece = area / (samples * samples) as f32;
+
+                // Centroids of the upright and then upside down pieces.
+                let upright = (0..samples).flat_map(|u| {
+                    (0..samples - u).map(move |v| (u as f32 + 1.0 / 3.0, v as f32 + 1.0 / 3.0))
+                });
+                let flipped = (0..samples).flat_map(|u| {
+                    (0..samples - u - 1).map(move |v| (u as f32 + 2.0 / 3.0, v as f32 + 2.0 / 3.0))
+                });
+
+                for (u, v) in upright.chain(flipped) {
+                    let (u, v) = (u / samples as f32, v / samples as f32);
+                    let point = centre + (a - centre) * u + (b - centre) * v;
+                    // Points inside a fan triangle are never degenerate.
+                    let under = upper.locate(point).unwrap() as u32;
+
+                    match above[tile].iter_mut().find(|(x, _)| *x == under) {
+                        Some((_, x)) => *x += piece,
+                        None => above[tile].push((under, piece)),
+                    }
+                    upper_areas[under as usize] += piece;
+                }
+
+                lower_areas[tile] += area;
+            }
+        }
+
+        let mut below = vec![SmallVec::<[(u32, f32); 8]>::new(); upper.len()];
+        for (tile, overlapping) in above.iter_mut().enumerate() {
+            for (upper_tile, area) in overlapping.iter_mut() {
+                below[*upper_tile as usize]
+                    .push((tile as u32, *area / upper_areas[*upper_tile as usize]));
+                *area /= lower_areas[tile];
+            }
+        }
+
+        Self { above, below }
+    }
+
+    /// The upper tiles over `lower_tile`, with the fraction of
+    /// `lower_tile` each one covers.
+    pub fn above(&self, lower_tile: usize) -> &[(u32, f32)] {
+        &self.above[lower_tile]
+    }
+
+    /// The lower tiles under `upper_tile`, with the fraction of
+    /// `upper_tile` each one covers.
+    pub fn below(&self, upper_tile: usize) -> &[(u32, f32)] {
+        &self.below[upper_tile]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbours_are_mutual() {
+        for &subdivisions in &[0, 1, 8, 13] {
+            let topology = HexSphereTopology::new(subdivisions);
+            assert!(!topology.is_empty());
+            for tile in 0..topology.len() {
+                let neighbours = topology.neighbours(tile);
+                assert_eq!(neighbours.len(), topology.tile_corners(tile).len());
+                for &neighbour in neighbours {
+                    assert!(topology
+                        .neighbours(neighbour as usize)
+                        .contains(&(tile as u32)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn builds_are_identical() {
+        for &subdivisions in &[0, 8, 13] {
+            assert!(HexSphereTopology::new(subdivisions) == HexSphereTopology::new(subdivisions));
+        }
+    }
+
+    #[test]
+    fn twelve_pentagons() {
+        for &subdivisions in &[0, 1, 8, 13] {
+            let topology = HexSphereTopology::new(subdivisions);
+            assert_eq!(topology.pentagons().count(), 12);
+            assert!(topology.pentagons().all(|tile| topology.neighbours(tile).len() == 5));
+        }
+    }
+
+    #[test]
+    fn centres_locate_their_tile() {
+        for &subdivisions in &[0, 1, 8, 33] {
+            let topology = HexSphereTopology::new(subdivisions);
+            for tile in 0..topology.len() {
+                assert_eq!(topology.locate(topology.centres()[tile]), Some(tile));
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_directions_locate_nothing() {
+        let topology = HexSphereTopology::new(8);
+        assert_eq!(topology.locate(Vec3A::ZERO), None);
+        assert_eq!(topology.locate(Vec3A::new(f32::NAN, 0.0, 1.0)), None);
+        assert_eq!(topology.locate(Vec3A::new(f32::INFINITY, 0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn overlaps_cover_both_shells() {
+        let sums = |overlapping: &[(u32, f32)]| overlapping.iter().map(|&(_, x)| x).sum::<f32>();
+
+        for &(lower, upper) in &[(1, 20), (8, 13), (13, 8)] {
+            let lower = HexSphereTopology::new(lower);
+            let upper = HexSphereTopology::new(upper);
+            let overlaps = TileOverlaps::new(&lower, &upper);
+
+            for tile in 0..lower.len() {
+                assert!((sums(overlaps.above(tile)) - 1.0).abs() < 1e-4);
+            }
+            for tile in 0..upper.len() {
+                assert!((sums(overlaps.below(tile)) - 1.0).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn spherical_areas_cover_the_sphere() {
+        for &subdivisions in &[0, 8, 13] {
+            let topology = HexSphereTopology::new(subdivisions);
+            let total = (0..topology.len())
+                .map(|tile| topology.spherical_area(tile))
+                .sum::<f32>();
+            assert!((total - 4.0 * std::f32::consts::PI).abs() < 1e-2, "{}", total);
+
+            // A tile's flat polygon is inside the sphere.
+            for tile in 0..topology.len() {
+                assert!(topology.planar_area(tile) < topology.spherical_area(tile));
+            }
+        }
+    }
+
+    #[test]
+    fn other_shapes() {
+        // The number of tiles with 3, 4 and 5 sides. The cube's faces
+        // are split in two, so half of its corners have 5 sides.
+        let shapes = [
+            (BaseShape::Icosahedron, [0, 0, 12]),
+            (BaseShape::NormalizedIcosahedron, [0, 0, 12]),
+            (BaseShape::Tetrahedron, [4, 0, 0]),
+            (BaseShape::Cube, [0, 4, 4]),
+        ];
+
+        for &(shape, counts) in &shapes {
+            for &subdivisions in &[0, 3, 8] {
+                let topology = HexSphereTopology::with_shape(subdivisions, shape);
+                assert!(topology == HexSphereTopology::with_shape(subdivisions, shape));
+
+                let mut sides = [0; 3];
+                topology
+                    .non_hexagons()
+                    .for_each(|tile| sides[topology.sides(tile) - 3] += 1);
+                assert_eq!(sides, counts);
+
+                for tile in 0..topology.len() {
+                    for &neighbour in topology.neighbours(tile) {
+                        assert!(topology
+                            .neighbours(neighbour as usize)
+                            .contains(&(tile as u32)));
+                    }
+                    assert_eq!(topology.locate(topology.centres()[tile]), Some(tile));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_go_clockwise() {
+        let topology = HexSphereTopology::new(8);
+        for tile in 0..topology.len() {
+            let centre = topology.centres()[tile];
+            let (x, z) = tangent_frame(centre);
+            let corners = topology.tile_corners(tile);
+            let corner = |i: usize| topology.corner_points()[corners[i % corners.len()] as usize];
+
+            // Clockwise turns `x` towards `z`, so the angle of each
+            // edge's middle keeps increasing from edge 0.
+            let angles = (0..corners.len())
+                .map(|i| {
+                    let middle = corner(i) + corner(i + 1) - centre * 2.0;
+                    let angle = middle.dot(z).atan2(middle.dot(x));
+                    if angle < 0.0 {
+                        angle + std::f32::consts::TAU
+                    } else {
+                        angle
+                    }
+                })
+                .collect::<Vec<_>>();
+            assert!(angles.windows(2).all(|x| x[0] < x[1]), "{:?}", angles);
+        }
+    }
+
+    #[test]
+    fn back_edges_lead_back() {
+        let topology = HexSphereTopology::new(8);
+        for tile in 0..topology.len() {
+            let corners = topology.tile_corners(tile);
+            for (edge, &neighbour) in topology.neighbours(tile).iter().enumerate() {
+                let neighbour = neighbour as usize;
+                let back = topology.back_edge(tile, edge);
+                assert_eq!(topology.neighbours(neighbour)[back] as usize, tile);
+                assert_eq!(topology.edge_towards(tile, neighbour), Some(edge));
+
+                // The shared edge, the other way around.
+                let other = topology.tile_corners(neighbour);
+                assert_eq!(corners[edge], other[(back + 1) % other.len()]);
+                assert_eq!(corners[(edge + 1) % corners.len()], other[back]);
+            }
+        }
+        assert_eq!(topology.turn(0, 0, -1), topology.neighbours(0).len() - 1);
+    }
+}
@@ -4,12 +4,13 @@ use bevy::prelude::*;
 use bevy::asset::LoadState;
 use shaders::LowPolyPBRPlugin;
 use sphereorder::{
-    BoardInitializationType, FaceMaterialIdx, GeographicalParams, NeighbourOf, OldFaceMaterialIdx,
-    PlanetDesc, SkyParams,
+    BaseShape, Board, BoardInitializationType, ElevationParams, FaceMaterialIdx,
+    GeographicalParams, LodParams, NeighbourOf, OldFaceMaterialIdx, PlanetDesc, Shading,
+    ShellSize, SkyParams, UvParams, VariantSelection,
 };
 use bevy::ecs::component::{ComponentDescriptor, StorageType};
 use sphereorder::camera::{SphereCamera, update_camera_transform, move_cameras, added_camera, CameraDebugPoint, DebugPoint, CameraSpeedConfig, LayerChangeEvent};
-use sphereorder::board_ops::Layers;
+use sphereorder::board_ops::{GenerationProgress, Layers};
 // use bevy_inspector_egui::InspectorPlugin;
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
@@ -70,6 +71,9 @@ fn poll_repeating_textures_load(
     server: Res<AssetServer>,
     mut textures: ResMut<Assets<Texture>>,
     mut state: ResMut<State<GameState>>,
+    progress: Res<GenerationProgress>,
+    unbuilt: Query<(), (With<PlanetDesc>, Without<Board>)>,
+    mut last_percent: Local<usize>,
 ) {
     loading
         .0
@@ -84,7 +88,13 @@ fn poll_repeating_textures_load(
             LoadState::Unloaded => panic!(),
         });
 
-    if loading.0.is_empty() {
+    let percent = (progress.fraction * 100.0) as usize;
+    if percent != *last_percent {
+        info!("Generating planets: {}%", percent);
+        *last_percent = percent;
+    }
+
+    if loading.0.is_empty() && progress.is_finished() && unbuilt.iter().next().is_none() {
         state.set(GameState::Game).unwrap();
     }
 }
@@ -104,7 +114,15 @@ fn setup(
             .spawn()
             .insert(PlanetDesc {
                 subvidisions: 13,
+                shape: BaseShape::Icosahedron,
                 planet_type: BoardInitializationType::Base(GeographicalParams { temp_seed: 1, metal_seed: 2 }),
+                size: ShellSize::EdgeLength(0.6),
+                elevation: Some(ElevationParams { seed: 7, scale: 1.5, amplitude: 0.03 }),
+                lod: Some(LodParams { subdivisions: 4, distance: 4.0 }),
+                shading: Shading::Flat,
+                uv: UvParams::default(),
+                variant_seed: 1,
+                variant_selection: VariantSelection::Noise,
             })
             .insert(RotationAxis(Vec3::X))
             .id(),
@@ -113,7 +131,15 @@ fn setup(
             .spawn()
             .insert(PlanetDesc {
                 subvidisions: 18,
+                shape: BaseShape::Icosahedron,
                 planet_type: BoardInitializationType::Sky(SkyParams { land_seed: 3 }),
+                size: ShellSize::EdgeLength(0.6),
+                elevation: None,
+                lod: Some(LodParams { subdivisions: 6, distance: 4.0 }),
+                shading: Shading::Smooth,
+                uv: UvParams::default(),
+                variant_seed: 2,
+                variant_selection: VariantSelection::Random,
             })
             .insert(RotationAxis(Vec3::Y))
             .id(),
@@ -122,7 +148,15 @@ fn setup(
             .spawn()
             .insert(PlanetDesc {
                 subvidisions: 23,
+                shape: BaseShape::Icosahedron,
                 planet_type: BoardInitializationType::Sky(SkyParams { land_seed: 4 }),
+                size: ShellSize::EdgeLength(0.6),
+                elevation: None,
+                lod: Some(LodParams { subdivisions: 8, distance: 4.0 }),
+                shading: Shading::Smooth,
+                uv: UvParams::default(),
+                variant_seed: 3,
+                variant_selection: VariantSelection::Random,
             })
             .insert(RotationAxis(Vec3::Z))
             .id(),
@@ -131,7 +165,15 @@ fn setup(
             .spawn()
             .insert(PlanetDesc {
                 subvidisions: 28,
+                shape: BaseShape::Icosahedron,
                 planet_type: BoardInitializationType::Sky(SkyParams { land_seed: 5 }),
+                size: ShellSize::EdgeLength(0.6),
+                elevation: None,
+                lod: Some(LodParams { subdivisions: 9, distance: 4.0 }),
+                shading: Shading::Smooth,
+                uv: UvParams::default(),
+                variant_seed: 4,
+                variant_selection: VariantSelection::Random,
             })
             .insert(RotationAxis(Vec3::new((2.0_f32).sqrt().recip(), (2.0_f32).sqrt().recip(), 0.0)))
             .id(),
@@ -140,7 +182,15 @@ fn setup(
             .spawn()
             .insert(PlanetDesc {
                 subvidisions: 33,
+                shape: BaseShape::Icosahedron,
                 planet_type: BoardInitializationType::Sky(SkyParams { land_seed: 6 }),
+                size: ShellSize::EdgeLength(0.6),
+                elevation: None,
+                lod: Some(LodParams { subdivisions: 11, distance: 4.0 }),
+                shading: Shading::Smooth,
+                uv: UvParams::default(),
+                variant_seed: 5,
+                variant_selection: VariantSelection::Random,
             })
             .insert(RotationAxis(Vec3::new((2.0_f32).sqrt().recip(), 0.0, (2.0_f32).sqrt().recip())))
             .id(),